@@ -0,0 +1,66 @@
+//! Mine placement and full board generation on large boards, run with `cargo bench`.
+
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+
+// the game is a binary, so the modules that generation needs are compiled into the benchmark
+#[allow(dead_code)]
+#[path = "../src/constraints.rs"]
+mod constraints;
+#[allow(dead_code)]
+#[path = "../src/field.rs"]
+mod field;
+#[allow(dead_code)]
+#[path = "../src/generate.rs"]
+mod generate;
+#[allow(dead_code)]
+#[path = "../src/seed.rs"]
+mod seed;
+
+use field::Field;
+use generate::{FieldGenerationOptions, GeneratorRegistry, RandomMineSelector};
+
+fn options(size: usize, mine_count: usize, generator: &str) -> FieldGenerationOptions {
+    FieldGenerationOptions {
+        width: size,
+        height: size,
+        mine_count,
+        seed: 42,
+        generator: generator.to_owned(),
+        ..Default::default()
+    }
+}
+
+fn select_mines(c: &mut Criterion) {
+    let registry = GeneratorRegistry::default();
+    let mut group = c.benchmark_group("select_mines");
+    group.sample_size(10);
+    for name in registry.names().filter(|&name| name != generate::STABLE_V1_GENERATOR) {
+        let options = options(4000, 3_000_000, name);
+        group.bench_with_input(BenchmarkId::new(name, "4000x4000/3M"), &options, |b, options| {
+            b.iter(|| registry.selector(options).get_mines(options))
+        });
+    }
+    group.finish();
+}
+
+fn generate_field(c: &mut Criterion) {
+    let registry = GeneratorRegistry::default();
+    let mut group = c.benchmark_group("generate");
+    group.sample_size(10);
+    for size in [30, 500, 2000] {
+        let options = options(size, size * size / 5, generate::STABLE_GENERATOR);
+        group.bench_with_input(BenchmarkId::from_parameter(format!("{}x{}", size, size)), &options, |b, options| {
+            b.iter(|| -> Field { registry.generator(options).generate(options) })
+        });
+    }
+    // the whole path for the board of `select_mines`, building the cells costs more than placing
+    // the mines
+    let options = options(4000, 3_000_000, generate::STABLE_GENERATOR);
+    group.bench_with_input(BenchmarkId::from_parameter("4000x4000/3M"), &options, |b, options| {
+        b.iter(|| -> Field { registry.generator(options).generate(options) })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, select_mines, generate_field);
+criterion_main!(benches);
@@ -0,0 +1,144 @@
+use std::f32::consts::PI;
+use bevy::prelude::*;
+use crate::field::{CellHandle, CellState, Field, Mark};
+use crate::render::{apply_look, Look};
+use crate::settings::Settings;
+use crate::{ColorMaterial, TextFont};
+
+/// Delay between two rings of cells revealed by a flood fill, in seconds.
+const RIPPLE_STEP: f32 = 0.03;
+/// Delay between two rings of mines going off after a loss, in seconds.
+const EXPLOSION_STEP: f32 = 0.06;
+/// Upper bound for any delay so large boards don't keep the player waiting.
+const MAX_DELAY: f32 = 1.0;
+
+/// Cell the last reveal started from, the reveal ripple spreads out from here.
+#[derive(Debug, Default)]
+pub struct RippleOrigin(pub Option<CellHandle>);
+
+/// State of the cell as it was last handed to the renderer, used to tell real changes apart from
+/// redraws.
+#[derive(Component, Debug, Clone, Copy)]
+pub(crate) struct ShownState(pub CellState);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum AnimationKind {
+    /// The cell turns around its vertical axis and shows the new look on the back.
+    Flip,
+    /// The flag grows in with a small overshoot.
+    Pop,
+    /// The mine swells up and settles again.
+    Explode,
+}
+
+impl AnimationKind {
+    fn duration(self) -> f32 {
+        match self {
+            AnimationKind::Flip => 0.25,
+            AnimationKind::Pop => 0.2,
+            AnimationKind::Explode => 0.3,
+        }
+    }
+
+    /// Point of the animation, from 0 to 1, at which the new look replaces the old one.
+    fn swap_at(self) -> f32 {
+        match self {
+            AnimationKind::Flip => 0.5,
+            AnimationKind::Pop | AnimationKind::Explode => 0.0,
+        }
+    }
+
+    fn scale(self, t: f32) -> Vec3 {
+        match self {
+            AnimationKind::Flip => Vec3::new((1.0 - 2.0 * t).abs(), 1.0, 1.0),
+            AnimationKind::Pop => Vec3::splat(t + 0.2 * (PI * t).sin()),
+            AnimationKind::Explode => Vec3::splat(1.0 + 0.4 * (PI * t).sin()),
+        }
+    }
+}
+
+/// Tween played on a cell entity. The new look is held back until the animation reaches its swap
+/// point, so delayed cells keep showing their old state.
+#[derive(Component, Debug)]
+pub(crate) struct CellAnimation {
+    kind: AnimationKind,
+    delay: f32,
+    elapsed: f32,
+    look: Option<Look>,
+}
+
+impl CellAnimation {
+    fn new(kind: AnimationKind, delay: f32) -> Self {
+        Self {
+            kind,
+            delay: delay.min(MAX_DELAY),
+            elapsed: 0.0,
+            look: None,
+        }
+    }
+
+    pub fn with_look(mut self, look: Look) -> Self {
+        self.look = Some(look);
+        self
+    }
+}
+
+fn distance(a: CellHandle, b: CellHandle) -> f32 {
+    let dx = a.x as f32 - b.x as f32;
+    let dy = a.y as f32 - b.y as f32;
+    (dx * dx + dy * dy).sqrt()
+}
+
+/// Picks the animation for a cell that changed from `shown` to its current state, `None` if the
+/// change should be drawn right away.
+pub(crate) fn animation_for(field: &Field, handle: CellHandle, shown: Option<&ShownState>, settings: &Settings, origin: &RippleOrigin) -> Option<CellAnimation> {
+    let state = field[handle].get_state();
+    let previous = shown.map_or(CellState::Hidden, |s| s.0);
+    if !settings.animations || previous == state {
+        return None;
+    }
+    match state {
+        CellState::Revealed if field[handle].is_mine && field.detonated().is_some() => {
+            let detonated = field.detonated().unwrap_or(handle);
+            Some(CellAnimation::new(AnimationKind::Explode, distance(detonated, handle) * EXPLOSION_STEP))
+        }
+        CellState::Revealed => {
+            let delay = origin.0.map_or(0.0, |origin| distance(origin, handle) * RIPPLE_STEP);
+            Some(CellAnimation::new(AnimationKind::Flip, delay))
+        }
+        CellState::Marked(Mark::Mine) => Some(CellAnimation::new(AnimationKind::Pop, 0.0)),
+        _ => None,
+    }
+}
+
+pub(crate) fn animate_cells(mut commands: Commands,
+                            time: Res<Time>,
+                            settings: Res<Settings>,
+                            mut query: Query<(Entity, &mut CellAnimation, &mut Transform, Option<&Handle<ColorMaterial>>, Option<&mut TextureAtlasSprite>, Option<&Children>)>,
+                            mut materials: ResMut<Assets<ColorMaterial>>,
+                            font: Res<TextFont>) {
+    for (e, mut animation, mut transform, material, sprite, childs) in query.iter_mut() {
+        animation.elapsed += time.delta_seconds();
+        let t = if settings.animations {
+            ((animation.elapsed - animation.delay) / animation.kind.duration()).min(1.0)
+        } else {
+            // turning animations off finishes the running ones
+            1.0
+        };
+        if t < 0.0 {
+            continue;
+        }
+
+        if t >= animation.kind.swap_at() {
+            if let Some(look) = animation.look.take() {
+                apply_look(&mut commands, e, look, material, sprite, childs, &mut materials, &font);
+            }
+        }
+        if t >= 1.0 {
+            transform.scale = Vec3::ONE;
+            commands.entity(e).remove::<CellAnimation>();
+        } else {
+            transform.scale = animation.kind.scale(t);
+        }
+    }
+}
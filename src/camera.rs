@@ -0,0 +1,56 @@
+use bevy::prelude::*;
+use crate::{CameraOptions, FieldGenerationOptions, MainCam};
+
+pub(crate) const MIN_ZOOM: f32 = 1.0;
+pub(crate) const MAX_ZOOM: f32 = 8.0;
+
+/// Zoom level and focus point of the main camera. A zoom of 1.0 shows the whole board, the focus
+/// is the point in world space the camera tries to keep in the middle of the screen.
+#[derive(Debug)]
+pub struct CameraView {
+    pub zoom: f32,
+    pub focus: Vec2,
+}
+
+impl Default for CameraView {
+    fn default() -> Self {
+        Self {
+            zoom: MIN_ZOOM,
+            focus: Vec2::ZERO,
+        }
+    }
+}
+
+impl CameraView {
+    pub fn zoom_by(&mut self, factor: f32) {
+        self.zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+    }
+}
+
+pub(crate) fn reset_camera_view(mut view: ResMut<CameraView>, camera_options: Res<CameraOptions>, field_options: Res<FieldGenerationOptions>) {
+    // keep the view when coming back from the pause screen
+    if !field_options.is_changed() {
+        return;
+    }
+    view.zoom = MIN_ZOOM;
+    view.focus = Vec2::new(camera_options.width / 2.0, camera_options.height / 2.0);
+}
+
+pub(crate) fn apply_camera_view(view: Res<CameraView>,
+                                camera_options: Res<CameraOptions>,
+                                mut query: Query<(&mut OrthographicProjection, &mut Transform), With<MainCam>>) {
+    if !view.is_changed() {
+        return;
+    }
+    for (mut projection, mut transform) in query.iter_mut() {
+        projection.scale = camera_options.scale / view.zoom;
+
+        // with a bottom left window origin the projection spans (0, 0) to (right, top) times scale
+        let visible = Vec2::new(projection.right, projection.top) * projection.scale;
+        let max = (Vec2::new(camera_options.width, camera_options.height) - visible).max(Vec2::ZERO);
+        let origin = (view.focus - visible / 2.0).clamp(Vec2::ZERO, max);
+        transform.translation.x = origin.x;
+        transform.translation.y = origin.y;
+    }
+}
+
@@ -0,0 +1,45 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+const CONFIG_FOLDER: &str = "./config";
+
+fn config_path(name: &str) -> PathBuf {
+    PathBuf::from(CONFIG_FOLDER).join(format!("{}.ron", name))
+}
+
+/// Reads `config/<name>.ron`. A missing file is reported as `io::ErrorKind::NotFound`, a file that
+/// can't be parsed as `io::ErrorKind::InvalidData`.
+fn load_config<T: DeserializeOwned>(name: &str) -> io::Result<T> {
+    let content = fs::read_to_string(config_path(name))?;
+    ron::from_str(&content).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Reads `config/<name>.ron`, a missing file gives the default. A file that can't be read or
+/// parsed gives the default as well and is reported through the returned error, which the caller
+/// logs in its own words. The browser build has no config folder and always starts from the
+/// default.
+pub(crate) fn load_or_default<T: Default + DeserializeOwned>(name: &str) -> (T, Option<io::Error>) {
+    if cfg!(target_arch = "wasm32") {
+        return (T::default(), None);
+    }
+    match load_config(name) {
+        Ok(value) => (value, None),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => (T::default(), None),
+        Err(e) => (T::default(), Some(e)),
+    }
+}
+
+/// Writes `config/<name>.ron`. Does nothing in the browser build, values only last for the session
+/// there.
+pub(crate) fn save_config<T: Serialize>(name: &str, value: &T) -> io::Result<()> {
+    if cfg!(target_arch = "wasm32") {
+        return Ok(());
+    }
+    let content = ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::new())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    fs::create_dir_all(CONFIG_FOLDER)?;
+    fs::write(config_path(name), content)
+}
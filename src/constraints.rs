@@ -0,0 +1,212 @@
+use bevy::log::warn;
+use crate::generate::{floyd_sample, FieldGenerationOptions, MineSet};
+
+/// How many mines are moved at most, per mine on the board, while trying to satisfy
+/// [`GenerationConstraints::max_number`] and [`GenerationConstraints::no_isolated_mines`].
+const REPAIR_MOVES_PER_MINE: usize = 20;
+/// Random cells looked at for each move before the move is skipped.
+const REPAIR_TARGET_TRIES: usize = 50;
+
+/// Rectangle of cells, `x` and `y` are the cell coordinates of its lower left corner.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Zone {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Zone {
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        x >= self.x && x < self.x + self.width && y >= self.y && y < self.y + self.height
+    }
+}
+
+/// A zone in which mines are `weight` times as likely as elsewhere. Weights of overlapping regions
+/// multiply, a weight of zero keeps the zone free of mines.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WeightedRegion {
+    pub zone: Zone,
+    pub weight: f32,
+}
+
+/// Rules for a board beyond its mine count. The default places the mines uniformly over the whole
+/// board.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GenerationConstraints {
+    /// Share of the cells in percent that hold a mine, replaces the mine count if set.
+    pub density: Option<f32>,
+    /// Zones that never hold a mine.
+    pub exclusions: Vec<Zone>,
+    pub weighted_regions: Vec<WeightedRegion>,
+    /// Highest number a revealed cell may show.
+    pub max_number: Option<u8>,
+    /// Every mine has at least one mine as neighbour.
+    pub no_isolated_mines: bool,
+}
+
+impl GenerationConstraints {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Number of mines for a board of `len` cells that asks for `mine_count` mines.
+    pub fn mine_count(&self, mine_count: usize, len: usize) -> usize {
+        match self.density {
+            Some(density) => ((len as f32 * density.clamp(0.0, 100.0) / 100.0).round() as usize).min(len),
+            None => mine_count.min(len),
+        }
+    }
+
+    fn weight(&self, x: usize, y: usize) -> f32 {
+        if self.exclusions.iter().any(|zone| zone.contains(x, y)) {
+            return 0.0;
+        }
+        self.weighted_regions.iter()
+            .filter(|region| region.zone.contains(x, y))
+            .map(|region| region.weight.max(0.0))
+            .product()
+    }
+}
+
+/// Places the mines of `options` honouring its constraints, drawing every random number from
+/// `below`, which returns a number in `0..n`. Without constraints this is plain Floyd sampling
+/// over all cells, so unconstrained boards of the stable generator keep their documented layout.
+pub fn place_mines(options: &FieldGenerationOptions, mut below: impl FnMut(usize) -> usize) -> MineSet {
+    let len = options.width * options.height;
+    let constraints = &options.constraints;
+    if constraints.is_empty() {
+        return floyd_sample(len, options.mine_count, below);
+    }
+
+    let weights = (0..len)
+        .map(|i| constraints.weight(i / options.height, i % options.height))
+        .collect::<Vec<_>>();
+    let eligible = (0..len).filter(|&i| weights[i] > 0.0).collect::<Vec<_>>();
+    let count = constraints.mine_count(options.mine_count, len);
+    if count > eligible.len() {
+        warn!("Only {} of {} mines fit outside of the excluded zones", eligible.len(), count);
+    }
+    let count = count.min(eligible.len());
+
+    let mut mines = if constraints.weighted_regions.is_empty() {
+        let picked = floyd_sample(eligible.len(), count, &mut below);
+        let mut mines = MineSet::new(len);
+        for (i, &cell) in eligible.iter().enumerate() {
+            if picked.contains(i) {
+                mines.insert(cell);
+            }
+        }
+        mines
+    } else {
+        weighted_sample(len, &eligible, &weights, count, &mut below)
+    };
+
+    if constraints.max_number.is_some() || constraints.no_isolated_mines {
+        repair(options, &eligible, &mut mines, count, &mut below);
+    }
+    mines
+}
+
+/// Weighted sampling without replacement (Efraimidis and Spirakis): every cell gets the key
+/// `ln(u) / weight` for a uniform `u` and the cells with the largest keys become mines.
+fn weighted_sample(len: usize,
+                   eligible: &[usize],
+                   weights: &[f32],
+                   count: usize,
+                   below: &mut impl FnMut(usize) -> usize) -> MineSet {
+    // small enough for the 32 bit usize of the web build
+    const UNIT: usize = 1 << 30;
+    let mut keys = eligible.iter()
+        .map(|&i| {
+            let u = (below(UNIT) + 1) as f64 / UNIT as f64;
+            (u.ln() / weights[i] as f64, i)
+        })
+        .collect::<Vec<_>>();
+    let mut mines = MineSet::new(len);
+    if count == 0 {
+        return mines;
+    }
+    keys.select_nth_unstable_by(count - 1, |a, b| b.0.total_cmp(&a.0));
+    for &(_, i) in &keys[..count] {
+        mines.insert(i);
+    }
+    mines
+}
+
+/// Moves mines until the number and isolation rules hold or the move budget runs out. Only cells
+/// around a moved mine are checked again, so large boards stay fast.
+fn repair(options: &FieldGenerationOptions,
+          eligible: &[usize],
+          mines: &mut MineSet,
+          count: usize,
+          below: &mut impl FnMut(usize) -> usize) {
+    let board = Board { width: options.width, height: options.height };
+    let max = options.constraints.max_number.map_or(8, |max| max as usize);
+    let no_isolated = options.constraints.no_isolated_mines;
+    let mut queued = vec![true; board.len()];
+    let mut queue = (0..board.len()).rev().collect::<Vec<_>>();
+    let mut moves = count.max(1) * REPAIR_MOVES_PER_MINE;
+    while let Some(i) = queue.pop() {
+        queued[i] = false;
+        let adjacent = board.neighbors(i).filter(|&n| mines.contains(n));
+        let from = if mines.contains(i) {
+            if !no_isolated || adjacent.count() > 0 {
+                continue;
+            }
+            i
+        } else if adjacent.clone().count() > max {
+            // one of the mines that push the number too high
+            adjacent.last().unwrap()
+        } else {
+            continue;
+        };
+        if moves == 0 {
+            warn!("Could not satisfy the generation constraints, the board breaks some of them");
+            return;
+        }
+        moves -= 1;
+
+        mines.remove(from);
+        let target = (0..REPAIR_TARGET_TRIES)
+            .map(|_| eligible[below(eligible.len())])
+            .find(|&to| to != from && !mines.contains(to) && board.accepts(mines, to, max, no_isolated));
+        let to = target.unwrap_or(from);
+        mines.insert(to);
+        for cell in [i, from, to].into_iter().chain(board.neighbors(from)).chain(board.neighbors(to)) {
+            if !queued[cell] {
+                queued[cell] = true;
+                queue.push(cell);
+            }
+        }
+    }
+}
+
+struct Board {
+    width: usize,
+    height: usize,
+}
+
+impl Board {
+    fn len(&self) -> usize {
+        self.width * self.height
+    }
+
+    fn neighbors(&self, i: usize) -> impl Iterator<Item = usize> + Clone {
+        let (x, y) = ((i / self.height) as isize, (i % self.height) as isize);
+        let (width, height) = (self.width as isize, self.height as isize);
+        (-1..=1)
+            .flat_map(move |dx| (-1..=1).map(move |dy| (x + dx, y + dy)))
+            .filter(move |&(nx, ny)| (nx, ny) != (x, y) && nx >= 0 && ny >= 0 && nx < width && ny < height)
+            .map(move |(nx, ny)| nx as usize * height as usize + ny as usize)
+    }
+
+    /// Whether a mine on `to` keeps every neighbour at or below `max` and, if required, has a
+    /// mine next to it.
+    fn accepts(&self, mines: &MineSet, to: usize, max: usize, no_isolated: bool) -> bool {
+        let crowded = self.neighbors(to)
+            .filter(|&n| !mines.contains(n))
+            .any(|n| self.neighbors(n).filter(|&m| mines.contains(m)).count() + 1 > max);
+        !crowded && (!no_isolated || self.neighbors(to).any(|n| mines.contains(n)))
+    }
+}
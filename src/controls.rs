@@ -0,0 +1,367 @@
+use std::fmt::{Display, Formatter};
+use std::io;
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+use bevy_egui::egui::{Align2, Color32, Frame, Grid, RichText, ScrollArea};
+use bevy_egui::egui::style::Margin;
+use leafwing_input_manager::prelude::InputMap;
+use leafwing_input_manager::user_input::InputButton;
+use serde::{Deserialize, Serialize};
+use crate::config::{load_or_default, save_config};
+use crate::interactions::{FieldInteraction, GameInteractions};
+use crate::menu::MenuScreen;
+
+const CONTROLS_CONFIG: &str = "controls";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Binding {
+    Keyboard(KeyCode),
+    Mouse(MouseButton),
+    Gamepad(GamepadButtonType),
+}
+
+impl From<Binding> for InputButton {
+    fn from(binding: Binding) -> Self {
+        match binding {
+            Binding::Keyboard(key) => InputButton::Keyboard(key),
+            Binding::Mouse(button) => InputButton::Mouse(button),
+            Binding::Gamepad(button) => InputButton::Gamepad(button),
+        }
+    }
+}
+
+impl Display for Binding {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Binding::Keyboard(key) => write!(f, "Key {:?}", key),
+            Binding::Mouse(button) => write!(f, "Mouse {:?}", button),
+            Binding::Gamepad(button) => write!(f, "Pad {:?}", button),
+        }
+    }
+}
+
+/// Where an action can be triggered. Two actions only conflict if they share a binding and can be
+/// triggered at the same time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Context {
+    Board,
+    Menu,
+    Global,
+}
+
+impl Context {
+    fn overlaps(self, other: Context) -> bool {
+        self == other || self == Context::Global || other == Context::Global
+    }
+}
+
+fn game_context(action: &GameInteractions) -> Context {
+    match action {
+        GameInteractions::NewGame | GameInteractions::RestartGame | GameInteractions::OpenMenu => Context::Global,
+        _ => Context::Menu,
+    }
+}
+
+/// Identifies one action of either input map while it is being rebound.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ActionSlot {
+    Field(usize),
+    Game(usize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conflict {
+    pub binding: Binding,
+    pub first: String,
+    pub second: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ControlsConfig {
+    pub field: Vec<(FieldInteraction, Vec<Binding>)>,
+    pub game: Vec<(GameInteractions, Vec<Binding>)>,
+}
+
+impl Default for ControlsConfig {
+    fn default() -> Self {
+        use Binding::*;
+        Self {
+            field: vec![
+                (FieldInteraction::Reveal, vec![Mouse(MouseButton::Left), Keyboard(KeyCode::Space), Keyboard(KeyCode::Return), Gamepad(GamepadButtonType::South)]),
+                (FieldInteraction::ToggleMark, vec![Mouse(MouseButton::Right), Keyboard(KeyCode::F), Gamepad(GamepadButtonType::East)]),
+                (FieldInteraction::Chord, vec![Mouse(MouseButton::Middle), Keyboard(KeyCode::C), Gamepad(GamepadButtonType::West)]),
+                // giving up counts as a loss, so it is only bound on request and offered on the pause screen
+                (FieldInteraction::RevealAll, vec![]),
+                (FieldInteraction::CursorUp, vec![Keyboard(KeyCode::Up), Keyboard(KeyCode::W), Keyboard(KeyCode::K), Gamepad(GamepadButtonType::DPadUp)]),
+                (FieldInteraction::CursorDown, vec![Keyboard(KeyCode::Down), Keyboard(KeyCode::S), Keyboard(KeyCode::J), Gamepad(GamepadButtonType::DPadDown)]),
+                (FieldInteraction::CursorLeft, vec![Keyboard(KeyCode::Left), Keyboard(KeyCode::A), Keyboard(KeyCode::H), Gamepad(GamepadButtonType::DPadLeft)]),
+                (FieldInteraction::CursorRight, vec![Keyboard(KeyCode::Right), Keyboard(KeyCode::D), Keyboard(KeyCode::L), Gamepad(GamepadButtonType::DPadRight)]),
+                (FieldInteraction::ZoomIn, vec![Keyboard(KeyCode::Equals), Gamepad(GamepadButtonType::RightTrigger)]),
+                (FieldInteraction::ZoomOut, vec![Keyboard(KeyCode::Minus), Gamepad(GamepadButtonType::LeftTrigger)]),
+            ],
+            game: vec![
+                (GameInteractions::NewGame, vec![Keyboard(KeyCode::N), Keyboard(KeyCode::F2), Gamepad(GamepadButtonType::Select)]),
+                (GameInteractions::RestartGame, vec![Keyboard(KeyCode::R)]),
+                (GameInteractions::OpenMenu, vec![Keyboard(KeyCode::Escape), Gamepad(GamepadButtonType::Start)]),
+                (GameInteractions::MenuUp, vec![Keyboard(KeyCode::Up), Gamepad(GamepadButtonType::DPadUp)]),
+                (GameInteractions::MenuDown, vec![Keyboard(KeyCode::Down), Gamepad(GamepadButtonType::DPadDown)]),
+                (GameInteractions::MenuLeft, vec![Keyboard(KeyCode::Left), Gamepad(GamepadButtonType::DPadLeft)]),
+                (GameInteractions::MenuRight, vec![Keyboard(KeyCode::Right), Gamepad(GamepadButtonType::DPadRight)]),
+                (GameInteractions::MenuSelect, vec![Keyboard(KeyCode::Return), Gamepad(GamepadButtonType::South)]),
+            ],
+        }
+    }
+}
+
+impl ControlsConfig {
+    /// Loads `config/controls.ron`. Actions missing from the file keep their default bindings, so
+    /// older files stay usable when new actions are added.
+    pub fn load_or_default() -> Self {
+        let (loaded, error) = load_or_default::<ControlsConfig>(CONTROLS_CONFIG);
+        if let Some(e) = error {
+            warn!("Could not load controls, using defaults: {}", e);
+        }
+        let mut config = ControlsConfig::default();
+        for (action, bindings) in loaded.field {
+            if let Some(entry) = config.field.iter_mut().find(|(a, _)| *a == action) {
+                entry.1 = bindings;
+            }
+        }
+        for (action, bindings) in loaded.game {
+            if let Some(entry) = config.game.iter_mut().find(|(a, _)| *a == action) {
+                entry.1 = bindings;
+            }
+        }
+        config
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        save_config(CONTROLS_CONFIG, self)
+    }
+
+    pub fn field_input_map(&self) -> InputMap<FieldInteraction> {
+        let mut input_map = InputMap::default();
+        for (action, bindings) in &self.field {
+            for binding in bindings {
+                input_map.insert(action.clone(), InputButton::from(*binding));
+            }
+        }
+        input_map
+    }
+
+    pub fn game_input_map(&self) -> InputMap<GameInteractions> {
+        let mut input_map = InputMap::default();
+        for (action, bindings) in &self.game {
+            for binding in bindings {
+                input_map.insert(action.clone(), InputButton::from(*binding));
+            }
+        }
+        input_map
+    }
+
+    fn bindings_mut(&mut self, slot: ActionSlot) -> &mut Vec<Binding> {
+        match slot {
+            ActionSlot::Field(i) => &mut self.field[i].1,
+            ActionSlot::Game(i) => &mut self.game[i].1,
+        }
+    }
+
+    /// Every pair of actions that share a binding while being active at the same time.
+    pub fn conflicts(&self) -> Vec<Conflict> {
+        let entries = self.field.iter()
+            .flat_map(|(action, bindings)| bindings.iter().map(move |b| (Context::Board, format!("{:?}", action), *b)))
+            .chain(self.game.iter()
+                .flat_map(|(action, bindings)| bindings.iter().map(move |b| (game_context(action), format!("{:?}", action), *b))))
+            .collect::<Vec<_>>();
+
+        let mut conflicts = Vec::new();
+        for (i, (context, action, binding)) in entries.iter().enumerate() {
+            for (other_context, other_action, other_binding) in &entries[i + 1..] {
+                if binding == other_binding && context.overlaps(*other_context) && action != other_action {
+                    conflicts.push(Conflict {
+                        binding: *binding,
+                        first: action.clone(),
+                        second: other_action.clone(),
+                    });
+                }
+            }
+        }
+        conflicts
+    }
+}
+
+/// Binding currently waiting for input on the controls screen. `index` is `None` when a new
+/// binding is added instead of replacing an existing one.
+#[derive(Debug, Default)]
+pub(crate) struct Rebinding {
+    pub target: Option<(ActionSlot, Option<usize>)>,
+    pub message: Option<String>,
+}
+
+impl Rebinding {
+    pub fn is_capturing(&self) -> bool {
+        self.target.is_some()
+    }
+}
+
+pub(crate) fn capture_binding(mut rebinding: ResMut<Rebinding>,
+                              mut controls: ResMut<ControlsConfig>,
+                              keys: Res<Input<KeyCode>>,
+                              mouse: Res<Input<MouseButton>>,
+                              gamepad: Res<Input<GamepadButton>>,
+                              mut armed: Local<bool>) {
+    let (slot, index) = match rebinding.target {
+        Some(target) => target,
+        None => {
+            *armed = false;
+            return;
+        }
+    };
+    // a controller clicks the rebind button on press, that press must not become the binding
+    if !*armed {
+        *armed = true;
+        return;
+    }
+
+    if keys.just_pressed(KeyCode::Escape) {
+        rebinding.target = None;
+        rebinding.message = None;
+        return;
+    }
+
+    let binding = keys.get_just_pressed().next().map(|k| Binding::Keyboard(*k))
+        .or_else(|| mouse.get_just_pressed().next().map(|b| Binding::Mouse(*b)))
+        .or_else(|| gamepad.get_just_pressed().next().map(|b| Binding::Gamepad(b.1)));
+
+    if let Some(binding) = binding {
+        let bindings = controls.bindings_mut(slot);
+        match index {
+            Some(i) => bindings[i] = binding,
+            None if !bindings.contains(&binding) => bindings.push(binding),
+            None => {}
+        }
+        rebinding.target = None;
+        rebinding.message = None;
+    }
+}
+
+pub(crate) fn apply_controls(controls: Res<ControlsConfig>,
+                             mut field_maps: Query<&mut InputMap<FieldInteraction>>,
+                             mut game_maps: Query<&mut InputMap<GameInteractions>>) {
+    if !controls.is_changed() || controls.is_added() {
+        return;
+    }
+    for mut map in field_maps.iter_mut() {
+        let gamepad = map.gamepad();
+        *map = controls.field_input_map();
+        if let Some(gamepad) = gamepad {
+            map.set_gamepad(gamepad);
+        }
+    }
+    for mut map in game_maps.iter_mut() {
+        let gamepad = map.gamepad();
+        *map = controls.game_input_map();
+        if let Some(gamepad) = gamepad {
+            map.set_gamepad(gamepad);
+        }
+    }
+}
+
+fn binding_row(ui: &mut egui::Ui, name: String, slot: ActionSlot, bindings: &[Binding], conflicts: &[Conflict], rebinding: &mut Rebinding) -> Option<usize> {
+    let mut removed = None;
+    ui.label(RichText::new(name).size(20.0));
+    ui.horizontal(|ui| {
+        for (i, binding) in bindings.iter().enumerate() {
+            let text = if rebinding.target == Some((slot, Some(i))) {
+                RichText::new("...").color(Color32::YELLOW)
+            } else if conflicts.iter().any(|c| c.binding == *binding) {
+                RichText::new(binding.to_string()).color(Color32::RED)
+            } else {
+                RichText::new(binding.to_string())
+            };
+            if ui.button(text).clicked() {
+                rebinding.target = Some((slot, Some(i)));
+            }
+            if ui.small_button("x").clicked() {
+                removed = Some(i);
+            }
+        }
+        if ui.button("+").clicked() {
+            rebinding.target = Some((slot, None));
+        }
+    });
+    ui.end_row();
+    removed
+}
+
+pub(crate) fn show_controls(mut egui_ctx: ResMut<EguiContext>,
+                            mut screen: ResMut<MenuScreen>,
+                            mut controls: ResMut<ControlsConfig>,
+                            mut rebinding: ResMut<Rebinding>) {
+    if *screen != MenuScreen::Controls {
+        return;
+    }
+    let conflicts = controls.conflicts();
+    let ctx = egui_ctx.ctx_mut();
+    egui::Area::new("Controls")
+        .anchor(Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+        .show(ctx, |ui| {
+            Frame::none()
+                .inner_margin(Margin::same(5.0))
+                .fill(egui::Color32::from_rgba_unmultiplied(0, 125, 125, 220))
+                .rounding(0.15)
+                .show(ui, |ui| {
+                    ui.vertical(|ui| {
+                        ui.heading(RichText::new("Controls").size(50.0));
+
+                        ScrollArea::vertical().max_height(500.0).show(ui, |ui| {
+                            Grid::new("controls_grid").show(ui, |ui| {
+                                let mut removed = None;
+                                for (i, (action, bindings)) in controls.field.iter().enumerate() {
+                                    let slot = ActionSlot::Field(i);
+                                    if let Some(b) = binding_row(ui, format!("{:?}", action), slot, bindings, &conflicts, &mut rebinding) {
+                                        removed = Some((slot, b));
+                                    }
+                                }
+                                for (i, (action, bindings)) in controls.game.iter().enumerate() {
+                                    let slot = ActionSlot::Game(i);
+                                    if let Some(b) = binding_row(ui, format!("{:?}", action), slot, bindings, &conflicts, &mut rebinding) {
+                                        removed = Some((slot, b));
+                                    }
+                                }
+                                if let Some((slot, b)) = removed {
+                                    controls.bindings_mut(slot).remove(b);
+                                }
+                            });
+                        });
+
+                        if rebinding.is_capturing() {
+                            ui.label(RichText::new("Press a key, mouse or controller button (Esc to cancel)").color(Color32::YELLOW));
+                        }
+                        for conflict in &conflicts {
+                            ui.label(RichText::new(format!("{} is used by {} and {}", conflict.binding, conflict.first, conflict.second))
+                                .color(Color32::RED));
+                        }
+                        if let Some(message) = &rebinding.message {
+                            ui.label(message.clone());
+                        }
+
+                        ui.horizontal(|ui| {
+                            if ui.add_enabled(conflicts.is_empty(), egui::Button::new(RichText::new("Save").size(30.0))).clicked() {
+                                rebinding.message = Some(match controls.save() {
+                                    Ok(()) => "Controls saved".to_owned(),
+                                    Err(e) => format!("Could not save controls: {}", e),
+                                });
+                            }
+                            if ui.button(RichText::new("Defaults").size(30.0)).clicked() {
+                                *controls = ControlsConfig::default();
+                            }
+                            if ui.button(RichText::new("Back").size(30.0)).clicked() {
+                                rebinding.target = None;
+                                *screen = MenuScreen::Main;
+                            }
+                        });
+                    });
+                });
+        });
+}
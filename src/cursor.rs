@@ -0,0 +1,160 @@
+use bevy::prelude::*;
+use bevy::sprite::MaterialMesh2dBundle;
+use leafwing_input_manager::prelude::ActionState;
+use crate::{CameraOptions, CellHandle, CellOptions, Field, FieldGenerationOptions};
+use crate::camera::CameraView;
+use crate::interactions::FieldInteraction;
+
+const REPEAT_DELAY: f32 = 0.35;
+const REPEAT_RATE: f32 = 0.08;
+const STICK_DEADZONE: f32 = 0.5;
+const ZOOM_STEP: f32 = 1.25;
+
+/// Cell selected with the keyboard. The cursor becomes `active` when it is moved with a key and
+/// stays active until the mouse is moved again, so interactions know which input to follow.
+#[derive(Debug, Default)]
+pub struct CellCursor {
+    pub handle: CellHandle,
+    pub active: bool,
+}
+
+#[derive(Component, Debug)]
+pub(crate) struct CursorHighlight;
+
+/// Direction currently held and the timer until the cursor moves again in that direction.
+#[derive(Default)]
+pub(crate) struct CursorRepeat {
+    held: Option<(isize, isize)>,
+    timer: Timer,
+}
+
+pub(crate) fn init_cursor(mut commands: Commands,
+                          field_options: Res<FieldGenerationOptions>,
+                          camera_options: Res<CameraOptions>,
+                          mut cursor: ResMut<CellCursor>,
+                          mut materials: ResMut<Assets<ColorMaterial>>,
+                          mut meshes: ResMut<Assets<Mesh>>,
+                          old: Query<Entity, With<CursorHighlight>>) {
+    if !field_options.is_changed() {
+        return;
+    }
+    for old in old.iter() {
+        commands.entity(old).despawn_recursive();
+    }
+
+    cursor.handle = CellHandle {
+        x: field_options.width / 2,
+        y: field_options.height / 2,
+    };
+
+    let cell_options = CellOptions::from_field_dimension_and_camera_options((field_options.width, field_options.height), &camera_options);
+    let mesh = meshes.add(Mesh::from(shape::Quad::new(Vec2::new(cell_options.width, cell_options.height))));
+
+    commands.spawn_bundle(MaterialMesh2dBundle {
+        mesh: mesh.into(),
+        material: materials.add(ColorMaterial::from(Color::rgba(1.0, 0.85, 0.0, 0.5))),
+        transform: Transform::default().with_translation(cell_options.get_translation(cursor.handle) + Vec3::Z),
+        visibility: Visibility { is_visible: cursor.active },
+        ..default()
+    }).insert(CursorHighlight);
+}
+
+pub(crate) fn update_cursor(query: Query<&ActionState<FieldInteraction>>,
+                            mut cursor: ResMut<CellCursor>,
+                            mut repeat: Local<CursorRepeat>,
+                            field: Res<Field>,
+                            time: Res<Time>,
+                            gamepads: Res<Gamepads>,
+                            axes: Res<Axis<GamepadAxis>>,
+                            mut mouse_moved: EventReader<CursorMoved>) {
+    let action = query.single();
+    let mouse_moved = mouse_moved.iter().count() > 0;
+
+    let mut dx: isize = 0;
+    let mut dy: isize = 0;
+    if action.pressed(FieldInteraction::CursorLeft) {
+        dx -= 1;
+    }
+    if action.pressed(FieldInteraction::CursorRight) {
+        dx += 1;
+    }
+    if action.pressed(FieldInteraction::CursorDown) {
+        dy -= 1;
+    }
+    if action.pressed(FieldInteraction::CursorUp) {
+        dy += 1;
+    }
+    for gamepad in gamepads.iter() {
+        let x = axes.get(GamepadAxis(*gamepad, GamepadAxisType::LeftStickX)).unwrap_or(0.0);
+        let y = axes.get(GamepadAxis(*gamepad, GamepadAxisType::LeftStickY)).unwrap_or(0.0);
+        if x.abs() > STICK_DEADZONE {
+            dx = x.signum() as isize;
+        }
+        if y.abs() > STICK_DEADZONE {
+            dy = y.signum() as isize;
+        }
+    }
+
+    if dx == 0 && dy == 0 {
+        repeat.held = None;
+        if mouse_moved {
+            cursor.active = false;
+        }
+        return;
+    }
+
+    let step = if repeat.held != Some((dx, dy)) {
+        repeat.held = Some((dx, dy));
+        repeat.timer = Timer::from_seconds(REPEAT_DELAY, false);
+        true
+    } else if repeat.timer.tick(time.delta()).finished() {
+        repeat.timer = Timer::from_seconds(REPEAT_RATE, false);
+        true
+    } else {
+        false
+    };
+
+    if !step {
+        return;
+    }
+    // the first key press only brings the cursor back, it does not move it yet
+    if cursor.active {
+        cursor.handle = CellHandle {
+            x: (cursor.handle.x as isize + dx).clamp(0, field.width as isize - 1) as usize,
+            y: (cursor.handle.y as isize + dy).clamp(0, field.height as isize - 1) as usize,
+        };
+    }
+    cursor.active = true;
+}
+
+pub(crate) fn update_zoom(query: Query<&ActionState<FieldInteraction>>,
+                          cursor: Res<CellCursor>,
+                          field: Res<Field>,
+                          camera_options: Res<CameraOptions>,
+                          mut view: ResMut<CameraView>) {
+    let action = query.single();
+    if action.just_pressed(FieldInteraction::ZoomIn) {
+        view.zoom_by(ZOOM_STEP);
+    }
+    if action.just_pressed(FieldInteraction::ZoomOut) {
+        view.zoom_by(1.0 / ZOOM_STEP);
+    }
+    if cursor.active && cursor.is_changed() {
+        let cell_options = CellOptions::from_field_dimension_and_camera_options((field.width, field.height), &camera_options);
+        view.focus = cell_options.get_translation(cursor.handle).truncate();
+    }
+}
+
+pub(crate) fn update_cursor_highlight(cursor: Res<CellCursor>,
+                                      field: Res<Field>,
+                                      camera_options: Res<CameraOptions>,
+                                      mut query: Query<(&mut Transform, &mut Visibility), With<CursorHighlight>>) {
+    if !cursor.is_changed() {
+        return;
+    }
+    let cell_options = CellOptions::from_field_dimension_and_camera_options((field.width, field.height), &camera_options);
+    for (mut transform, mut visibility) in query.iter_mut() {
+        transform.translation = cell_options.get_translation(cursor.handle) + Vec3::Z;
+        visibility.is_visible = cursor.active;
+    }
+}
@@ -0,0 +1,390 @@
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+use bevy_egui::egui::{Align2, Frame, Grid, RichText, Slider, TextEdit};
+use bevy_egui::egui::style::Margin;
+use iyes_loopless::state::NextState;
+use leafwing_input_manager::prelude::ActionState;
+use crate::{CameraOptions, CellComponent, GameState, MainCam, spawn_cells, StateMaterials};
+use crate::camera::{CameraView, MIN_ZOOM};
+use crate::field::{Cell, CellHandle, CellState, Field};
+use crate::generate::{BoardSource, FieldGenerationOptions};
+use crate::interactions::{FieldInteraction, GameInteractions, MousePositionToCellConverter};
+use crate::puzzle::{PuzzleBoard, PuzzleGenerator};
+use crate::skin::ActiveSkin;
+
+/// Board files are read and written below the asset folder, so saved boards can go straight into
+/// a level pack.
+const ASSET_FOLDER: &str = "assets";
+const MAX_SIZE: usize = 30;
+const BOARD_EXTENSION: &str = ".board.ron";
+
+/// What a click on a cell of the edited board paints. Clicking a cell that already shows the tile
+/// of the tool hides it again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditorTool {
+    Mine,
+    Open,
+    Hidden,
+}
+
+impl EditorTool {
+    const ALL: [EditorTool; 3] = [EditorTool::Mine, EditorTool::Open, EditorTool::Hidden];
+
+    fn tile(&self) -> char {
+        match self {
+            EditorTool::Mine => '*',
+            EditorTool::Open => 'o',
+            EditorTool::Hidden => '.',
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            EditorTool::Mine => "mine",
+            EditorTool::Open => "open",
+            EditorTool::Hidden => "hidden",
+        }
+    }
+}
+
+/// Board that is being edited, it is kept when the editor is left.
+pub struct EditorState {
+    pub board: PuzzleBoard,
+    pub tool: EditorTool,
+    /// File of the board, relative to the asset folder.
+    pub path: String,
+    /// Result of the last check, save or load.
+    pub status: Option<String>,
+    /// The shown field no longer matches the board.
+    dirty: bool,
+}
+
+impl Default for EditorState {
+    fn default() -> Self {
+        Self {
+            board: PuzzleBoard::empty(10, 10),
+            tool: EditorTool::Mine,
+            path: "levels/custom.board.ron".to_owned(),
+            status: None,
+            dirty: true,
+        }
+    }
+}
+
+impl EditorState {
+    /// File of the board relative to the asset folder, with the board extension added if it is
+    /// missing. Paths that would leave the asset folder are refused.
+    fn file(&self) -> Result<PathBuf, String> {
+        let mut path = self.path.trim().to_owned();
+        if !path.ends_with(BOARD_EXTENSION) {
+            path.push_str(BOARD_EXTENSION);
+        }
+        let path = PathBuf::from(path);
+        if !path.components().all(|component| matches!(component, Component::Normal(_) | Component::CurDir)) {
+            return Err("the file has to be inside the asset folder".to_owned());
+        }
+        Ok(path)
+    }
+
+    /// Writes the board and returns the file it was written to.
+    fn save(&self) -> Result<PathBuf, String> {
+        self.board.validate()?;
+        let file = self.file()?;
+        let content = ron::ser::to_string_pretty(&self.board, ron::ser::PrettyConfig::new()).map_err(|e| e.to_string())?;
+        let path = Path::new(ASSET_FOLDER).join(&file);
+        if let Some(folder) = path.parent() {
+            fs::create_dir_all(folder).map_err(|e| e.to_string())?;
+        }
+        fs::write(path, content).map_err(|e| e.to_string())?;
+        Ok(file)
+    }
+
+    /// Reads a board and returns it together with the file it was read from.
+    fn load(&self) -> Result<(PuzzleBoard, PathBuf), String> {
+        let file = self.file()?;
+        let content = fs::read_to_string(Path::new(ASSET_FOLDER).join(&file)).map_err(|e| e.to_string())?;
+        let board: PuzzleBoard = ron::from_str(&content).map_err(|e| e.to_string())?;
+        board.validate()?;
+        Ok((board, file))
+    }
+}
+
+/// Field that shows the edited board: mines and open cells are drawn revealed, hidden cells as
+/// they look at the start of the game.
+fn preview(board: &PuzzleBoard) -> Field {
+    let (width, height) = (board.width(), board.height());
+    let mut elements = Vec::with_capacity(width * height);
+    for x in 0..width {
+        for y in 0..height {
+            let mut cell = Cell::new();
+            cell.is_mine = board.tile(x, y) == '*';
+            elements.push(cell);
+        }
+    }
+    let mut field = Field::new(width, height, elements, board.mine_count());
+    for cell in field.get_handles() {
+        if board.tile(cell.x, cell.y) != '.' {
+            field.open(cell);
+        }
+    }
+    field.mark_all_changed();
+    field
+}
+
+/// Number of safe cells that can't be opened by logic alone, starting from the open cells and
+/// knowing the mine count. Zero means the board is solvable without guessing.
+///
+/// Every open cell states how many mines hide among its covered neighbours. A rule whose mines
+/// are all found or whose cells are all mines settles its cells, and a rule whose cells are part
+/// of another rule settles the remaining cells of the other rule the same way.
+fn count_guesses(board: &PuzzleBoard) -> usize {
+    let field = PuzzleGenerator::new(board).generate(&FieldGenerationOptions::default());
+    let height = field.height;
+    let index = |cell: CellHandle| cell.x * height + cell.y;
+    let handles = field.get_handles();
+    // `Some(true)` for a known mine, `Some(false)` for an opened cell
+    let mut known = handles.iter()
+        .map(|&cell| (field[cell].get_state() == CellState::Revealed).then(|| false))
+        .collect::<Vec<_>>();
+
+    loop {
+        let mut rules = Vec::new();
+        for &cell in &handles {
+            if known[index(cell)] != Some(false) {
+                continue;
+            }
+            let neighbors = field.get_neighbors(cell);
+            let mut cells = neighbors.iter().map(|&n| index(n)).filter(|&n| known[n].is_none()).collect::<Vec<_>>();
+            if cells.is_empty() {
+                continue;
+            }
+            cells.sort_unstable();
+            let mines = neighbors.iter().filter(|&&n| field[n].is_mine).count();
+            let found = neighbors.iter().filter(|&&n| known[index(n)] == Some(true)).count();
+            rules.push((cells, mines - found));
+        }
+        let covered = (0..known.len()).filter(|&i| known[i].is_none()).collect::<Vec<_>>();
+        let found = known.iter().filter(|&&k| k == Some(true)).count();
+        rules.push((covered, board.mine_count() - found));
+
+        let mut settled = Vec::new();
+        for (cells, mines) in &rules {
+            settle(cells, *mines, &mut settled);
+        }
+        if settled.is_empty() {
+            for (small, small_mines) in &rules {
+                for (large, large_mines) in &rules {
+                    if small.len() >= large.len() || !small.iter().all(|c| large.binary_search(c).is_ok()) {
+                        continue;
+                    }
+                    let rest = large.iter().copied().filter(|c| small.binary_search(c).is_err()).collect::<Vec<_>>();
+                    settle(&rest, large_mines - small_mines, &mut settled);
+                }
+            }
+        }
+        if settled.is_empty() {
+            break;
+        }
+        for (cell, mine) in settled {
+            known[cell] = Some(mine);
+        }
+    }
+
+    handles.iter()
+        .filter(|&&cell| !field[cell].is_mine && known[index(cell)].is_none())
+        .count()
+}
+
+/// Settles `cells` if they hold no mine or nothing but mines.
+fn settle(cells: &[usize], mines: usize, settled: &mut Vec<(usize, bool)>) {
+    if cells.is_empty() {
+        return;
+    }
+    if mines == 0 {
+        settled.extend(cells.iter().map(|&cell| (cell, false)));
+    } else if mines == cells.len() {
+        settled.extend(cells.iter().map(|&cell| (cell, true)));
+    }
+}
+
+pub(crate) fn enter_editor(mut commands: Commands,
+                           mut state: ResMut<EditorState>,
+                           mut view: ResMut<CameraView>,
+                           camera_options: Res<CameraOptions>) {
+    state.dirty = true;
+    commands.insert_resource(MousePositionToCellConverter::new());
+    view.zoom = MIN_ZOOM;
+    view.focus = Vec2::new(camera_options.width / 2.0, camera_options.height / 2.0);
+}
+
+/// Removes the preview of the edited board, it is built again from the editor state on return.
+pub(crate) fn leave_editor(mut commands: Commands, cells: Query<Entity, With<CellComponent>>) {
+    for cell in cells.iter() {
+        commands.entity(cell).despawn_recursive();
+    }
+    commands.remove_resource::<Field>();
+}
+
+/// Paints the tile of the selected tool on the cell below the mouse.
+pub(crate) fn paint_board(query: Query<&ActionState<FieldInteraction>>,
+                          mut state: ResMut<EditorState>,
+                          field: Option<Res<Field>>,
+                          mut egui_ctx: ResMut<EguiContext>,
+                          cam_options: Res<CameraOptions>,
+                          converter: Res<MousePositionToCellConverter>,
+                          cam_query: Query<(&Camera, &GlobalTransform), With<MainCam>>,
+                          windows: Res<Windows>) {
+    let action = query.single();
+    // clicks on the editor panel are meant for the panel
+    if !action.just_pressed(FieldInteraction::Reveal) || egui_ctx.ctx_mut().wants_pointer_input() {
+        return;
+    }
+    let field = match field {
+        Some(field) if !state.dirty => field,
+        _ => return,
+    };
+    let (cam, trans) = cam_query.single();
+    if let Some(cell) = converter.get_cell_coords(cam, trans, windows, &cam_options, &field) {
+        let tile = state.tool.tile();
+        let tile = if state.board.tile(cell.x, cell.y) == tile { '.' } else { tile };
+        state.board.set_tile(cell.x, cell.y, tile);
+        state.status = None;
+        state.dirty = true;
+    }
+}
+
+/// Shows the edited board, the cells are spawned again when the size changed.
+pub(crate) fn update_editor_field(mut commands: Commands,
+                                  mut state: ResMut<EditorState>,
+                                  field: Option<Res<Field>>,
+                                  mut materials: ResMut<Assets<ColorMaterial>>,
+                                  mut meshes: ResMut<Assets<Mesh>>,
+                                  camera_options: Res<CameraOptions>,
+                                  state_materials: Res<StateMaterials>,
+                                  skin: Res<ActiveSkin>,
+                                  old: Query<Entity, With<CellComponent>>) {
+    if !state.dirty {
+        return;
+    }
+    state.dirty = false;
+    let next = preview(&state.board);
+    let resized = field.map_or(true, |field| (field.width, field.height) != (next.width, next.height));
+    if resized || old.is_empty() {
+        for old in old.iter() {
+            commands.entity(old).despawn_recursive();
+        }
+        spawn_cells(&mut commands, &next, &camera_options, &skin, &state_materials, &mut materials, &mut meshes);
+    }
+    commands.insert_resource(next);
+}
+
+pub(crate) fn check_editor_actions(mut commands: Commands,
+                                   query: Query<&ActionState<GameInteractions>>,
+                                   mut egui_ctx: ResMut<EguiContext>) {
+    if egui_ctx.ctx_mut().wants_keyboard_input() {
+        return;
+    }
+    if query.single().just_pressed(GameInteractions::OpenMenu) {
+        commands.insert_resource(NextState(GameState::Menu));
+    }
+}
+
+pub(crate) fn show_editor(mut commands: Commands,
+                          mut egui_ctx: ResMut<EguiContext>,
+                          mut state: ResMut<EditorState>) {
+    let ctx = egui_ctx.ctx_mut();
+    egui::Area::new("Editor")
+        .anchor(Align2::LEFT_TOP, egui::vec2(10.0, 10.0))
+        .show(ctx, |ui| {
+            Frame::none()
+                .inner_margin(Margin::same(5.0))
+                .fill(egui::Color32::from_rgba_unmultiplied(0, 125, 125, 220))
+                .rounding(0.15)
+                .show(ui, |ui| {
+                    ui.vertical(|ui| {
+                        ui.heading(RichText::new("Editor").size(40.0));
+
+                        let (mut width, mut height) = (state.board.width(), state.board.height());
+                        Grid::new("editor_grid")
+                            .min_row_height(10.0)
+                            .show(ui, |ui| {
+                                ui.label(RichText::new("columns").size(20.0));
+                                ui.add(Slider::new(&mut width, 1..=MAX_SIZE));
+                                ui.end_row();
+
+                                ui.label(RichText::new("rows").size(20.0));
+                                ui.add(Slider::new(&mut height, 1..=MAX_SIZE));
+                                ui.end_row();
+
+                                ui.label(RichText::new("tool").size(20.0));
+                                ui.horizontal(|ui| {
+                                    for tool in EditorTool::ALL {
+                                        if ui.selectable_label(state.tool == tool, RichText::new(tool.name()).size(20.0)).clicked() {
+                                            state.tool = tool;
+                                        }
+                                    }
+                                });
+                                ui.end_row();
+
+                                ui.label(RichText::new("message").size(20.0));
+                                let mut message = state.board.message.clone().unwrap_or_default();
+                                if ui.add(TextEdit::singleline(&mut message).hint_text("none")).changed() {
+                                    state.board.message = Some(message).filter(|m| !m.is_empty());
+                                }
+                                ui.end_row();
+
+                                ui.label(RichText::new("file").size(20.0));
+                                ui.text_edit_singleline(&mut state.path);
+                                ui.end_row();
+                            });
+                        if (width, height) != (state.board.width(), state.board.height()) {
+                            state.board.resize(width, height);
+                            state.status = None;
+                            state.dirty = true;
+                        }
+
+                        ui.label(RichText::new(format!("{} mines", state.board.mine_count())).size(20.0));
+                        ui.horizontal(|ui| {
+                            if ui.button(RichText::new("Check").size(25.0)).clicked() {
+                                let guesses = count_guesses(&state.board);
+                                state.status = Some(match guesses {
+                                    0 => "Solvable without guessing".to_owned(),
+                                    n => format!("{} cells need a guess", n),
+                                });
+                            }
+                            if ui.button(RichText::new("Save").size(25.0)).clicked() {
+                                state.status = Some(match state.save() {
+                                    Ok(file) => format!("Saved {}", file.display()),
+                                    Err(e) => format!("Could not save: {}", e),
+                                });
+                            }
+                            if ui.button(RichText::new("Load").size(25.0)).clicked() {
+                                state.status = Some(match state.load() {
+                                    Ok((board, file)) => {
+                                        state.board = board;
+                                        state.dirty = true;
+                                        format!("Loaded {}", file.display())
+                                    }
+                                    Err(e) => format!("Could not load: {}", e),
+                                });
+                            }
+                            if ui.button(RichText::new("Clear").size(25.0)).clicked() {
+                                let (width, height) = (state.board.width(), state.board.height());
+                                state.board = PuzzleBoard::empty(width, height);
+                                state.status = None;
+                                state.dirty = true;
+                            }
+                        });
+                        if let Some(status) = &state.status {
+                            ui.label(RichText::new(status).size(20.0));
+                        }
+
+                        if ui.button(RichText::new("Back").size(30.0)).clicked() {
+                            commands.insert_resource(NextState(GameState::Menu));
+                        }
+                    });
+                });
+        });
+}
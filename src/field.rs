@@ -1,6 +1,6 @@
 use std::ops::{Index, IndexMut};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct CellHandle {
     pub x: usize,
     pub y: usize,
@@ -33,38 +33,76 @@ pub enum CellState {
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Mark {
-    Empty,
+    Question,
     Mine,
 }
 
 
 
+/// Low bits of `Cell::flags`, the state of the cell.
+const STATE_MASK: u8 = 0b011;
+/// High bit of `Cell::flags`, set while the cell waits in the change list.
+const QUEUED: u8 = 0b100;
+/// `Cell::adjacent_mines` of a cell whose neighbours weren't counted yet.
+const UNCOUNTED: u8 = u8::MAX;
+
+impl CellState {
+    fn to_bits(self) -> u8 {
+        match self {
+            CellState::Hidden => 0,
+            CellState::Marked(Mark::Question) => 1,
+            CellState::Marked(Mark::Mine) => 2,
+            CellState::Revealed => 3,
+        }
+    }
+
+    fn from_bits(bits: u8) -> Self {
+        match bits & STATE_MASK {
+            0 => CellState::Hidden,
+            1 => CellState::Marked(Mark::Question),
+            2 => CellState::Marked(Mark::Mine),
+            _ => CellState::Revealed,
+        }
+    }
+}
+
+/// A cell takes three bytes, the large boards have millions of them.
 #[derive(Debug, Clone)]
 pub struct Cell {
-    state: CellState,
+    /// The state and the queued bit.
+    flags: u8,
     pub is_mine: bool,
-    adjacent_mines: Option<usize>,
-    pub is_dirty: bool,
+    adjacent_mines: u8,
 }
 
 
 impl Cell {
     pub fn new() -> Self {
         Cell {
-            state: CellState::Hidden,
+            flags: CellState::Hidden.to_bits(),
             is_mine: false,
-            adjacent_mines: None,
-            is_dirty: false,
+            adjacent_mines: UNCOUNTED,
         }
     }
 
-    pub fn set_state(&mut self, state: CellState) {
-        self.state = state;
-        self.is_dirty = true;
+    pub fn get_state(&self) -> CellState {
+        CellState::from_bits(self.flags)
+    }
+
+    fn set_state(&mut self, state: CellState) {
+        self.flags = self.flags & !STATE_MASK | state.to_bits();
     }
 
-    pub fn get_state(&self) -> &CellState {
-        &self.state
+    fn is_queued(&self) -> bool {
+        self.flags & QUEUED != 0
+    }
+
+    fn set_queued(&mut self, queued: bool) {
+        if queued {
+            self.flags |= QUEUED;
+        } else {
+            self.flags &= !QUEUED;
+        }
     }
 
 }
@@ -81,38 +119,82 @@ pub struct Field {
     pub height: usize,
     elements: Vec<Cell>,
     count_unrevealed: usize,
+    mine_count: usize,
+    status: GameStatus,
+    changes: Vec<CellHandle>,
+}
+
+/// Progress of the game played on a field. Once the game is won, lost or given up the field
+/// rejects every further action.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameStatus {
+    NotStarted,
+    InProgress,
+    Won,
+    Lost { cell: CellHandle },
+    GaveUp,
+}
+
+impl GameStatus {
+    pub fn is_finished(&self) -> bool {
+        !matches!(self, GameStatus::NotStarted | GameStatus::InProgress)
+    }
 }
 
 pub enum RevealResult {
     Mine,
     Empty(usize),
     AlreadyRevealed,
+    GameEnded,
 }
 
 impl Field {
-    pub fn new(width: usize, height: usize, cells: Vec<Cell>) -> Self {
-        let count_unrevealed = cells.iter().filter(|c| c.state == CellState::Hidden).count();
+    /// A field of hidden `cells` with `mine_count` mines among them, the caller knows the count
+    /// from placing the mines. Cells that are open from the start are shown with [`Field::open`].
+    pub fn new(width: usize, height: usize, cells: Vec<Cell>, mine_count: usize) -> Self {
+        // only safe cells have to be revealed to win
+        let count_unrevealed = cells.len() - mine_count;
         Self {
             width,
             height,
             elements: cells,
             count_unrevealed,
+            mine_count,
+            status: GameStatus::NotStarted,
+            changes: Vec::new(),
         }
     }
 
     pub fn get_adjacent_mines(&mut self, cell: CellHandle) -> usize {
-        if let Some(adj) = self[cell].adjacent_mines {
-            adj
+        if self[cell].adjacent_mines != UNCOUNTED {
+            self[cell].adjacent_mines as usize
         } else {
             let neighbors = self.get_neighbors(cell);
             let adj = neighbors.iter().filter(|&&c| self[c].is_mine).count();
-            self[cell].adjacent_mines = Some(adj);
+            self[cell].adjacent_mines = adj as u8;
             adj
         }
     }
+
+    /// Shows `cell` before the game starts, as the open cells of hand-made boards.
+    pub fn open(&mut self, cell: CellHandle) {
+        if self[cell].get_state() == CellState::Revealed {
+            return;
+        }
+        self.set_state(cell, CellState::Revealed);
+        if !self[cell].is_mine {
+            self.count_unrevealed -= 1;
+        }
+    }
     
-    pub fn is_won(&self) -> bool {
-        self.elements.iter().filter(|c| !c.is_mine).all(|c| c.state == CellState::Revealed)
+    pub fn status(&self) -> GameStatus {
+        self.status
+    }
+
+    /// Mines minus flags, what the mine counter shows. Question marks don't count as flags.
+    pub fn remaining_mines(&self) -> isize {
+        let flags = self.elements.iter().filter(|c| c.get_state() == CellState::Marked(Mark::Mine)).count();
+        self.mine_count as isize - flags as isize
     }
 
     pub fn get_neighbors(&self, cell: CellHandle) -> Vec<CellHandle> {
@@ -143,53 +225,131 @@ impl Field {
 
 
     pub fn try_reveal(&self, cell: CellHandle) -> RevealResult {
+        if self.status.is_finished() {
+            return RevealResult::GameEnded;
+        }
         let c = &self[cell];
-        if c.state == CellState::Revealed {
+        // a flag protects its cell until it is removed, flood fill stops at it as well
+        if matches!(c.get_state(), CellState::Revealed | CellState::Marked(Mark::Mine)) {
             return RevealResult::AlreadyRevealed;
         }
         if c.is_mine {
             return RevealResult::Mine;
         }
 
-        if c.adjacent_mines.is_none() {
+        if c.adjacent_mines == UNCOUNTED {
             let neighbors = self.get_neighbors(cell);
             let surrounding_mines = neighbors.iter().filter(|&&n| self[n].is_mine).count();
             return RevealResult::Empty(surrounding_mines);
         }
 
-        RevealResult::Empty(c.adjacent_mines.expect("adjacent_mines should be set"))
+        RevealResult::Empty(c.adjacent_mines as usize)
     }
 
     pub fn reveal(&mut self, cell: CellHandle) -> RevealResult {
         let r = self.try_reveal(cell);
         match r {
             RevealResult::Mine => {
-                self[cell].set_state(CellState::Revealed);
+                self.set_state(cell, CellState::Revealed);
+                self.reveal_mines(cell);
             }
             RevealResult::Empty(adjacent_mines) => {
-                self[cell].set_state(CellState::Revealed);
-                self[cell].adjacent_mines = Some(adjacent_mines);
+                self.set_state(cell, CellState::Revealed);
+                self[cell].adjacent_mines = adjacent_mines as u8;
                 self.count_unrevealed -= 1;
+                self.status = if self.count_unrevealed == 0 {
+                    GameStatus::Won
+                } else {
+                    GameStatus::InProgress
+                };
             }
-            RevealResult::AlreadyRevealed => {
+            RevealResult::AlreadyRevealed | RevealResult::GameEnded => {
                 // do nothing
             }
         }
         r
     }
 
-    pub fn toggle_mark(&mut self, cell: CellHandle) {
-        let c = &mut self[cell];
+    fn set_state(&mut self, cell: CellHandle, state: CellState) {
+        self[cell].set_state(state);
+        self.mark_changed(cell);
+    }
 
-        match c.state {
+    /// Queues `cell` to be drawn again, every cell is queued at most once until the changes are taken.
+    pub fn mark_changed(&mut self, cell: CellHandle) {
+        if !self[cell].is_queued() {
+            self[cell].set_queued(true);
+            self.changes.push(cell);
+        }
+    }
+
+    /// Forces every cell to be drawn again, e.g. after the colours changed.
+    pub fn mark_all_changed(&mut self) {
+        for cell in self.get_handles() {
+            self.mark_changed(cell);
+        }
+    }
+
+    /// Cells whose state changed since the last call, in the order they changed.
+    pub fn take_changes(&mut self) -> Vec<CellHandle> {
+        let changes = std::mem::take(&mut self.changes);
+        for &cell in &changes {
+            self[cell].set_queued(false);
+        }
+        changes
+    }
+
+    /// The mine that ended the game, if one was revealed.
+    pub fn detonated(&self) -> Option<CellHandle> {
+        match self.status {
+            GameStatus::Lost { cell } => Some(cell),
+            _ => None,
+        }
+    }
+
+    /// Shows the board after `cell` exploded: every unflagged mine is revealed and wrongly flagged
+    /// cells are queued again so they can be drawn as mistakes.
+    fn reveal_mines(&mut self, cell: CellHandle) {
+        self.status = GameStatus::Lost { cell };
+        for handle in self.get_handles() {
+            let c = &self[handle];
+            match c.get_state() {
+                CellState::Hidden | CellState::Marked(Mark::Question) if c.is_mine => self.set_state(handle, CellState::Revealed),
+                CellState::Marked(Mark::Mine) if !c.is_mine => self.mark_changed(handle),
+                _ => {}
+            }
+        }
+    }
+
+    /// Reveals every cell of the board, used when the player gives up.
+    pub fn reveal_all(&mut self) {
+        if self.status.is_finished() {
+            return;
+        }
+        self.status = GameStatus::GaveUp;
+        for cell in self.get_handles() {
+            if self[cell].get_state() != CellState::Revealed {
+                self.set_state(cell, CellState::Revealed);
+            }
+        }
+        self.count_unrevealed = 0;
+    }
+
+    /// Cycles a hidden cell through flag and question mark, the question mark is skipped when
+    /// `question_marks` is off.
+    pub fn toggle_mark(&mut self, cell: CellHandle, question_marks: bool) {
+        if self.status.is_finished() {
+            return;
+        }
+        match self[cell].get_state() {
             CellState::Hidden => {
-                c.set_state( CellState::Marked(Mark::Mine));
+                self.set_state(cell, CellState::Marked(Mark::Mine));
             }
-            CellState::Marked(Mark::Mine) => {
-                c.set_state( CellState::Marked(Mark::Empty));
+            CellState::Marked(Mark::Mine) if question_marks => {
+                self.set_state(cell, CellState::Marked(Mark::Question));
             }
-            CellState::Marked(Mark::Empty) => {
-                c.set_state(CellState::Hidden);
+            CellState::Marked(_) => {
+                self.set_state(cell, CellState::Hidden);
             }
             _ => {
                 // do nothing
@@ -1,72 +1,299 @@
-use std::marker::PhantomData;
-#[cfg(feature = "fastrand")]
-use fastrand::u64;
-use crate::Field;
-#[cfg(feature = "rand")]
-use rand::{Rng, RngCore};
-#[cfg(feature = "rand")]
-use rand::seq::{IteratorRandom, SliceRandom};
-#[cfg(feature = "rand")]
+use rand::Rng;
+use rand::rngs::StdRng;
 use rand::prelude::{SeedableRng};
-#[cfg(feature = "rand_chacha")]
 use rand_chacha::{ChaCha8Rng};
+use bevy::log::warn;
+use crate::Field;
+use crate::constraints::{GenerationConstraints, place_mines};
 use crate::field::Cell;
+use crate::seed::SplitMix64;
+
+/// Cells of a board that hold a mine, one bit per cell index.
+#[derive(Debug, Clone)]
+pub struct MineSet {
+    words: Vec<u64>,
+    len: usize,
+}
+
+impl MineSet {
+    /// An empty set for a board of `len` cells.
+    pub fn new(len: usize) -> Self {
+        Self {
+            words: vec![0; (len + 63) / 64],
+            len,
+        }
+    }
+
+    /// Puts a mine on `index`, returns false if there already was one.
+    pub fn insert(&mut self, index: usize) -> bool {
+        assert!(index < self.len, "mine {} is outside of a board with {} cells", index, self.len);
+        let bit = 1 << (index % 64);
+        let word = &mut self.words[index / 64];
+        if *word & bit != 0 {
+            return false;
+        }
+        *word |= bit;
+        true
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        if index < self.len {
+            self.words[index / 64] &= !(1 << (index % 64));
+        }
+    }
 
-pub trait RandomMineSelector {
-    fn get_mines_index(options: &FieldGenerationOptions) -> Vec<usize>;
+    pub fn contains(&self, index: usize) -> bool {
+        index < self.len && self.words[index / 64] & (1 << (index % 64)) != 0
+    }
+
+    /// Indices of the mines in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words.iter().enumerate().flat_map(|(i, &word)| {
+            let mut rest = word;
+            std::iter::from_fn(move || {
+                if rest == 0 {
+                    return None;
+                }
+                let bit = rest.trailing_zeros() as usize;
+                rest &= rest - 1;
+                Some(i * 64 + bit)
+            })
+        })
+    }
 }
-pub struct FieldGenerator<M: RandomMineSelector>(PhantomData<M>);
 
-#[cfg(all(not(feature = "rand_chacha"), not(feature = "rand"), feature = "fastrand"))]
-pub type DefaultFieldGenerator = FieldGenerator<FastRandGenerator>;
+/// Picks `count` distinct cells out of `len` with Floyd's algorithm, which needs one random number
+/// per mine and no list of all cells. `below(n)` returns a number in `0..n`.
+pub(crate) fn floyd_sample(len: usize, count: usize, mut below: impl FnMut(usize) -> usize) -> MineSet {
+    let mut mines = MineSet::new(len);
+    for j in len - count.min(len)..len {
+        let t = below(j + 1);
+        if !mines.insert(t) {
+            mines.insert(j);
+        }
+    }
+    mines
+}
 
+/// Picks the mines of a board. Every selector owns the RNG it draws from, so several boards can be
+/// generated at the same time and a selector created from the same RNG state picks the same mines.
+/// Selectors are `Send` so boards can be generated on worker threads.
+pub trait RandomMineSelector: Send {
+    /// A random number in `0..n`.
+    fn below(&mut self, n: usize) -> usize;
 
-#[cfg(all(not(feature="rand_chacha"), feature = "rand"))]
-pub type DefaultFieldGenerator = FieldGenerator<ThreadRngFieldGenerator>;
+    /// Returns the mines of a board with `options.width * options.height` cells. Selectors that
+    /// place mines their own way still have to honour `options.constraints`, the default does so
+    /// with [`place_mines`].
+    fn get_mines(&mut self, options: &FieldGenerationOptions) -> MineSet {
+        place_mines(options, |n| self.below(n))
+    }
+}
 
-#[cfg(feature = "rand_chacha")]
-pub type DefaultFieldGenerator = FieldGenerator<ChaChaMineSelector>;
+impl RandomMineSelector for Box<dyn RandomMineSelector> {
+    fn below(&mut self, n: usize) -> usize {
+        (**self).below(n)
+    }
+
+    fn get_mines(&mut self, options: &FieldGenerationOptions) -> MineSet {
+        (**self).get_mines(options)
+    }
+}
+
+/// Builds the board for a set of options, either randomly or from a fixed layout.
+pub trait BoardSource {
+    fn generate(&mut self, options: &FieldGenerationOptions) -> Field;
+}
+
+pub struct FieldGenerator<M: RandomMineSelector> {
+    selector: M,
+}
 
 impl<M: RandomMineSelector> FieldGenerator<M> {
-    pub fn generate(options: Option<FieldGenerationOptions>) -> Field {
-        let options = options.unwrap_or_default();
-        let mut elements = Vec::with_capacity(options.width * options.height);
-        let mines = M::get_mines_index(&options);
-        for i in 0..options.width * options.height {
-            let mut cell = Cell::new();
-            if mines.contains(&i) {
-                cell.is_mine = true;
-            }
-            elements.push(cell);
+    pub fn new(selector: M) -> Self {
+        Self { selector }
+    }
+
+    pub fn generate(&mut self, options: &FieldGenerationOptions) -> Field {
+        let mines = self.selector.get_mines(options);
+        let mut elements = vec![Cell::new(); options.width * options.height];
+        let mut mine_count = 0;
+        for i in mines.iter() {
+            elements[i].is_mine = true;
+            mine_count += 1;
         }
-        Field::new(options.width, options.height, elements)
+        Field::new(options.width, options.height, elements, mine_count)
+    }
+}
+
+impl<M: RandomMineSelector> BoardSource for FieldGenerator<M> {
+    fn generate(&mut self, options: &FieldGenerationOptions) -> Field {
+        FieldGenerator::generate(self, options)
+    }
+}
+
+/// Name of the generator that implements the documented seed format, used for game codes without
+/// a generator.
+pub const STABLE_GENERATOR: &str = "stable";
+/// Name of the generator for boards of version 1 game codes.
+pub const STABLE_V1_GENERATOR: &str = "stable-v1";
+
+/// A generator that can be picked in the menu, created from the seed of a board.
+pub struct GeneratorEntry {
+    pub name: &'static str,
+    pub create: fn(u64) -> Box<dyn RandomMineSelector>,
+}
+
+/// Every mine selector the game knows, looked up by the name stored in [`FieldGenerationOptions`].
+pub struct GeneratorRegistry {
+    entries: Vec<GeneratorEntry>,
+}
+
+impl Default for GeneratorRegistry {
+    fn default() -> Self {
+        let mut registry = Self { entries: Vec::new() };
+        registry.register(STABLE_GENERATOR, |seed| Box::new(StableMineSelector::new(SplitMix64::new(seed))));
+        registry.register(STABLE_V1_GENERATOR, |seed| Box::new(StableV1MineSelector::new(SplitMix64::new(seed))));
+        registry.register("fastrand", |seed| Box::new(FastRandGenerator::new(fastrand::Rng::with_seed(seed))));
+        registry.register("stdrng", |seed| Box::new(RandMineSelector::new(StdRng::seed_from_u64(seed))));
+        registry.register("chacha8", |seed| Box::new(ChaChaMineSelector::with_seed(seed)));
+        registry
     }
 }
 
-#[cfg(feature = "fastrand")]
-pub struct FastRandGenerator;
+impl GeneratorRegistry {
+    /// Adds a generator, replacing one that is registered under the same name.
+    pub fn register(&mut self, name: &'static str, create: fn(u64) -> Box<dyn RandomMineSelector>) {
+        self.entries.retain(|entry| entry.name != name);
+        self.entries.push(GeneratorEntry { name, create });
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.entries.iter().map(|entry| entry.name)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.entries.iter().any(|entry| entry.name == name)
+    }
+
+    /// The generator `delta` entries away from `current`, wrapping around at both ends.
+    pub fn cycle(&self, current: &str, delta: isize) -> &'static str {
+        let count = self.entries.len() as isize;
+        let index = self.entries.iter().position(|entry| entry.name == current).unwrap_or(0) as isize;
+        self.entries[(index + delta).rem_euclid(count) as usize].name
+    }
+
+    /// Generator for the board described by `options`, seeded with its seed.
+    pub fn generator(&self, options: &FieldGenerationOptions) -> FieldGenerator<Box<dyn RandomMineSelector>> {
+        FieldGenerator::new(self.selector(options))
+    }
+
+    /// Mine selector of the generator named in `options`, seeded with its seed. Unknown names fall
+    /// back to the stable generator.
+    pub fn selector(&self, options: &FieldGenerationOptions) -> Box<dyn RandomMineSelector> {
+        let entry = self.entries.iter()
+            .find(|entry| entry.name == options.generator)
+            .or_else(|| {
+                warn!("Unknown generator {:?}, using {}", options.generator, STABLE_GENERATOR);
+                self.entries.iter().find(|entry| entry.name == STABLE_GENERATOR)
+            })
+            .expect("the stable generator is always registered");
+        (entry.create)(options.seed)
+    }
+}
+
+/// Implements the current version of the seed format documented in [`crate::seed`]. The output of
+/// this selector must never change for a given RNG state, add a new version instead.
+pub struct StableMineSelector {
+    rng: SplitMix64,
+}
+
+impl StableMineSelector {
+    pub fn new(rng: SplitMix64) -> Self {
+        Self { rng }
+    }
+}
+
+impl RandomMineSelector for StableMineSelector {
+    fn below(&mut self, n: usize) -> usize {
+        self.rng.below(n as u64) as usize
+    }
+}
+
+/// Version 1 of the seed format, kept so old game codes still give the same board. It shuffles a
+/// list of every cell and is too slow for large boards. Boards with constraints are placed like
+/// those of every other selector.
+pub struct StableV1MineSelector {
+    rng: SplitMix64,
+}
+
+impl StableV1MineSelector {
+    pub fn new(rng: SplitMix64) -> Self {
+        Self { rng }
+    }
+}
+
+impl RandomMineSelector for StableV1MineSelector {
+    fn below(&mut self, n: usize) -> usize {
+        self.rng.below(n as u64) as usize
+    }
+
+    fn get_mines(&mut self, options: &FieldGenerationOptions) -> MineSet {
+        if !options.constraints.is_empty() {
+            return place_mines(options, |n| self.below(n));
+        }
+        let len = options.width * options.height;
+        let mut vec = (0..len).collect::<Vec<_>>();
+        let mut mines = MineSet::new(len);
+        for i in 0..options.mine_count.min(len) {
+            let j = i + self.rng.below((len - i) as u64) as usize;
+            vec.swap(i, j);
+            mines.insert(vec[i]);
+        }
+        mines
+    }
+}
+
+/// Boards of this selector depend on the fastrand version, so its game codes only work between
+/// builds that use the same one.
+pub struct FastRandGenerator {
+    rng: fastrand::Rng,
+}
+
+impl FastRandGenerator {
+    pub fn new(rng: fastrand::Rng) -> Self {
+        Self { rng }
+    }
+}
 
-#[cfg(feature = "fastrand")]
 impl RandomMineSelector for FastRandGenerator {
-    fn get_mines_index(options: &FieldGenerationOptions) -> Vec<usize> {
-        fastrand::seed(options.seed);
-        let mut vec = (0..options.width * options.height).collect::<Vec<_>>();
-        fastrand::shuffle(&mut vec);
-        vec = vec.iter().take(options.mine_count).map(|u| *u).collect();
-        vec
+    fn below(&mut self, n: usize) -> usize {
+        self.rng.usize(..n)
+    }
+}
+
+/// Selects mines with any RNG of the `rand` crate.
+pub struct RandMineSelector<R: Rng> {
+    rng: R,
+}
+
+impl<R: Rng> RandMineSelector<R> {
+    pub fn new(rng: R) -> Self {
+        Self { rng }
+    }
+}
+
+impl<R: Rng + Send> RandomMineSelector for RandMineSelector<R> {
+    fn below(&mut self, n: usize) -> usize {
+        self.rng.gen_range(0..n)
     }
 }
 
-#[cfg(feature = "rand")]
-pub struct ThreadRngFieldGenerator {}
+pub type ChaChaMineSelector = RandMineSelector<ChaCha8Rng>;
 
-#[cfg(feature = "rand")]
-impl RandomMineSelector for ThreadRngFieldGenerator {
-    fn get_mines_index(options: &FieldGenerationOptions) -> Vec<usize> {
-        let mut rng = rand::thread_rng();
-        let mut vec: Vec<usize> = (0..options.width * options.height).into_iter().choose_multiple(&mut rng, options.mine_count);
-        vec.shuffle(&mut rng);
-        vec
+impl ChaChaMineSelector {
+    pub fn with_seed(seed: u64) -> Self {
+        Self::new(ChaCha8Rng::seed_from_u64(seed))
     }
 }
 
@@ -76,44 +303,115 @@ pub struct FieldGenerationOptions {
     pub height: usize,
     pub mine_count: usize,
     pub seed: u64,
+    /// Name of the generator in the [`GeneratorRegistry`].
+    pub generator: String,
+    pub constraints: GenerationConstraints,
+    /// Hand-made board to play instead of a generated one.
+    pub puzzle: Option<PuzzleRef>,
+}
+
+/// A level of one of the [`LevelPacks`](crate::puzzle::LevelPacks).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PuzzleRef {
+    pub pack: usize,
+    pub level: usize,
+}
+
+impl FieldGenerationOptions {
+    /// Whether a game code reproduces the board, which isn't the case for constrained or hand-made
+    /// boards.
+    pub fn has_game_code(&self) -> bool {
+        self.constraints.is_empty() && self.puzzle.is_none()
+    }
 }
 
 
 impl Default for FieldGenerationOptions {
-    #[cfg(feature = "fastrand")]
     fn default() -> Self {
-
-        let seed = u64(..u64::MAX);
         Self {
             mine_count: 10,
-            seed,
+            seed: fastrand::u64(..),
             width: 10,
             height: 10,
+            generator: STABLE_GENERATOR.to_owned(),
+            constraints: GenerationConstraints::default(),
+            puzzle: None,
         }
     }
+}
 
-    #[cfg(all( not(feature = "fastrand"), any(feature = "rand")))]
-    fn default() -> Self {
-        let mut rng = rand::thread_rng();
-        let seed = rng.next_u64();
-        Self {
-            mine_count: 10,
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seed::{GameCode, seed_from_str};
+
+    fn mines(generator: &str, width: usize, height: usize, mine_count: usize, seed: u64) -> Vec<usize> {
+        let options = FieldGenerationOptions {
+            width,
+            height,
+            mine_count,
             seed,
-            width: 10,
-            height: 10,
-        }
+            generator: generator.to_owned(),
+            ..Default::default()
+        };
+        let mines = GeneratorRegistry::default().selector(&options).get_mines(&options);
+        (0..width * height).filter(|&i| mines.contains(i)).collect()
     }
-}
 
-#[cfg(feature = "rand_chacha")]
-pub struct ChaChaMineSelector {}
+    // the expected layouts follow from the algorithm documented in `crate::seed`, they must only
+    // change together with the seed format version
+
+    #[test]
+    fn stable_layouts_are_fixed() {
+        assert_eq!(mines(STABLE_GENERATOR, 8, 8, 10, 0), [1, 6, 10, 15, 19, 24, 47, 48, 56, 60]);
+        assert_eq!(mines(STABLE_GENERATOR, 8, 8, 10, 42), [2, 8, 13, 15, 19, 21, 39, 40, 49, 52]);
+        assert_eq!(mines(STABLE_GENERATOR, 16, 16, 40, seed_from_str("minesweeper")), [
+            1, 4, 10, 12, 13, 19, 31, 35, 38, 44, 50, 54, 55, 58, 61, 63, 69, 76, 89, 90,
+            100, 103, 109, 113, 145, 173, 182, 189, 194, 198, 201, 203, 207, 208, 215, 217, 219, 229, 248, 254,
+        ]);
+    }
+
+    #[test]
+    fn stable_v1_layouts_are_fixed() {
+        assert_eq!(mines(STABLE_V1_GENERATOR, 8, 8, 10, 0), [3, 10, 16, 21, 24, 28, 50, 56, 61, 62]);
+        assert_eq!(mines(STABLE_V1_GENERATOR, 8, 8, 10, 42), [6, 11, 18, 19, 23, 27, 43, 47, 52, 56]);
+        assert_eq!(mines(STABLE_V1_GENERATOR, 16, 16, 40, seed_from_str("minesweeper")), [
+            0, 2, 17, 28, 29, 31, 35, 37, 42, 50, 51, 60, 65, 70, 71, 76, 77, 89, 91, 101,
+            106, 109, 119, 121, 128, 133, 164, 200, 204, 205, 208, 212, 217, 219, 221, 225, 227, 236, 254, 255,
+        ]);
+    }
+
+    #[test]
+    fn game_codes_reproduce_their_board() {
+        let code = "2-8x8-10-16".parse::<GameCode>().unwrap();
+        assert_eq!(code.seed, 42);
+        assert_eq!(mines(&code.generator, code.width, code.height, code.mine_count, code.seed), [2, 8, 13, 15, 19, 21, 39, 40, 49, 52]);
+
+        let code = "1-8x8-10-16".parse::<GameCode>().unwrap();
+        assert_eq!(mines(&code.generator, code.width, code.height, code.mine_count, code.seed), [6, 11, 18, 19, 23, 27, 43, 47, 52, 56]);
+    }
 
-#[cfg(feature = "rand_chacha")]
-impl RandomMineSelector for ChaChaMineSelector {
-    fn get_mines_index(options: &FieldGenerationOptions) -> Vec<usize> {
-        let mut rng = ChaCha8Rng::seed_from_u64(options.seed);
-        let mut vec: Vec<usize> = (0..options.width * options.height).into_iter().choose_multiple(&mut rng, options.mine_count);
-        vec.shuffle(&mut rng);
-        vec
+    #[test]
+    fn every_generator_repeats_its_board() {
+        for name in GeneratorRegistry::default().names() {
+            assert_eq!(mines(name, 16, 16, 40, 7), mines(name, 16, 16, 40, 7), "{}", name);
+            assert_eq!(mines(name, 16, 16, 40, 7).len(), 40, "{}", name);
+        }
+    }
+
+    #[test]
+    fn selectors_can_move_to_worker_threads() {
+        let options = FieldGenerationOptions::default();
+        let registry = GeneratorRegistry::default();
+        let handles = registry.names()
+            .map(|name| {
+                let mut selector = registry.selector(&FieldGenerationOptions { generator: name.to_owned(), ..options.clone() });
+                let options = options.clone();
+                std::thread::spawn(move || selector.get_mines(&options))
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            handle.join().unwrap();
+        }
     }
 }
@@ -0,0 +1,94 @@
+use bevy::prelude::*;
+use leafwing_input_manager::prelude::ActionState;
+use crate::{CameraOptions, CellEntities, CellHandle, CellState, ColorMaterial, Field, MainCam, Mark, StateMaterials};
+use crate::cursor::CellCursor;
+use crate::interactions::{FieldInteraction, MousePositionToCellConverter};
+use crate::skin::Tile;
+use crate::touch::TouchTracker;
+
+/// Cell under the mouse and the cells drawn pushed in while a chord is held. Both are painted on
+/// top of the regular look after the renderer ran.
+#[derive(Debug, Default, PartialEq)]
+pub struct Hover {
+    pub cell: Option<CellHandle>,
+    pub pressed: Vec<CellHandle>,
+}
+
+fn is_covered(field: &Field, cell: CellHandle) -> bool {
+    matches!(field[cell].get_state(), CellState::Hidden | CellState::Marked(Mark::Question))
+}
+
+pub(crate) fn update_hover(query: Query<&ActionState<FieldInteraction>>,
+                           mut field: ResMut<Field>,
+                           mut hover: ResMut<Hover>,
+                           cam_options: Res<CameraOptions>,
+                           converter: Res<MousePositionToCellConverter>,
+                           cursor: Res<CellCursor>,
+                           touch: Res<TouchTracker>,
+                           time: Res<Time>,
+                           cam_query: Query<(&Camera, &GlobalTransform), With<MainCam>>,
+                           windows: Res<Windows>) {
+    let action = query.single();
+    let (cam, trans) = cam_query.single();
+    let cell = if cursor.active {
+        Some(cursor.handle)
+    } else if touch.recently_touched(time.seconds_since_startup()) {
+        // a finger doesn't hover, the emulated mouse position would leave a stray highlight
+        None
+    } else {
+        converter.get_cell_coords(cam, trans, windows, &cam_options, &field)
+    };
+
+    let mut pressed = Vec::new();
+    if let Some(cell) = cell {
+        if action.pressed(FieldInteraction::Chord) && !field.status().is_finished() {
+            pressed = field.get_neighbors(cell).into_iter()
+                .chain(std::iter::once(cell))
+                .filter(|&n| is_covered(&field, n))
+                .collect();
+        }
+    }
+    let next = Hover { cell, pressed };
+    if *hover != next {
+        // let the renderer restore the cells that are no longer highlighted
+        hover.cell.iter().chain(hover.pressed.iter()).for_each(|&c| field.mark_changed(c));
+        *hover = next;
+    }
+}
+
+pub(crate) fn clear_hover(mut field: Option<ResMut<Field>>, mut hover: ResMut<Hover>) {
+    if let Some(field) = field.as_mut() {
+        hover.cell.iter().chain(hover.pressed.iter()).for_each(|&c| field.mark_changed(c));
+    }
+    *hover = Hover::default();
+}
+
+pub(crate) fn apply_hover(hover: Res<Hover>,
+                          field: Res<Field>,
+                          entities: Res<CellEntities>,
+                          colors: Res<StateMaterials>,
+                          mut query: Query<(Option<&Handle<ColorMaterial>>, Option<&mut TextureAtlasSprite>)>,
+                          mut materials: ResMut<Assets<ColorMaterial>>) {
+    if !hover.is_changed() && !field.is_changed() {
+        return;
+    }
+    let mut paint = |cell: CellHandle, color: Color, tile: Option<Tile>| {
+        if let Some(Ok((material, sprite))) = entities.get(&cell).map(|&e| query.get_mut(e)) {
+            if let Some(material) = material.and_then(|m| materials.get_mut(m)) {
+                material.color = color;
+            }
+            if let Some(mut sprite) = sprite {
+                match tile {
+                    Some(tile) => sprite.index = tile.index(),
+                    None => sprite.color = color,
+                }
+            }
+        }
+    };
+    if let Some(cell) = hover.cell.filter(|&c| is_covered(&field, c)) {
+        paint(cell, *colors.hover, None);
+    }
+    for &cell in hover.pressed.iter().filter(|&&c| is_covered(&field, c)) {
+        paint(cell, *colors.revealed, Some(Tile::Revealed));
+    }
+}
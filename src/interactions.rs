@@ -1,50 +1,216 @@
 use bevy::prelude::*;
+use bevy_egui::EguiContext;
 use iyes_loopless::prelude::NextState;
-use crate::{CameraOptions, CellHandle, Field, GameState, MainCam, RenderTarget, Revealer, RevealerImpl, RevealResult};
+use crate::{CameraOptions, CellHandle, Field, FieldGenerationOptions, GameState, MainCam, RenderTarget, Revealer, RevealerImpl, RevealResult};
+use crate::field::GameStatus;
+use crate::controls::Rebinding;
+use crate::animation::RippleOrigin;
+use crate::cursor::CellCursor;
+use crate::settings::Settings;
+use crate::sound::SoundEffect;
+use crate::touch::TouchTracker;
 
 use leafwing_input_manager::{Actionlike};
-use leafwing_input_manager::prelude::ActionState;
-use crate::menu::TitleText;
+use leafwing_input_manager::prelude::{ActionState, InputMap};
+use crate::menu::{TitleText, UiState};
+use serde::{Deserialize, Serialize};
 
-#[derive(Actionlike, Clone, Debug)]
+#[derive(Actionlike, Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
 pub enum FieldInteraction {
     Reveal,
     ToggleMark,
+    Chord,
     RevealAll,
+    CursorUp,
+    CursorDown,
+    CursorLeft,
+    CursorRight,
+    ZoomIn,
+    ZoomOut,
 }
 
-#[derive(Actionlike, Debug, Clone)]
+#[derive(Actionlike, Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
 pub enum GameInteractions {
     NewGame,
+    RestartGame,
+    OpenMenu,
+    MenuUp,
+    MenuDown,
+    MenuLeft,
+    MenuRight,
+    MenuSelect,
 }
 
+/// Sent by `check_game_status` when a game ends, `finish_game` turns it into the game over title
+/// and the statistics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameOutcome {
+    Won,
+    Lost,
+    GaveUp,
+}
+
+#[derive(Debug, Default)]
+pub struct GameStats {
+    pub won: usize,
+    pub lost: usize,
+}
+
+/// Binds gamepad buttons to the most recently connected controller.
+pub(crate) fn assign_gamepad(mut events: EventReader<GamepadEvent>,
+                             mut field_maps: Query<&mut InputMap<FieldInteraction>>,
+                             mut game_maps: Query<&mut InputMap<GameInteractions>>) {
+    for GamepadEvent(gamepad, event) in events.iter() {
+        match event {
+            GamepadEventType::Connected => {
+                info!("Gamepad {:?} connected", gamepad);
+                field_maps.iter_mut().for_each(|mut map| { map.set_gamepad(*gamepad); });
+                game_maps.iter_mut().for_each(|mut map| { map.set_gamepad(*gamepad); });
+            }
+            GamepadEventType::Disconnected => {
+                info!("Gamepad {:?} disconnected", gamepad);
+                field_maps.iter_mut().filter(|map| map.gamepad() == Some(*gamepad)).for_each(|mut map| { map.clear_gamepad(); });
+                game_maps.iter_mut().filter(|map| map.gamepad() == Some(*gamepad)).for_each(|mut map| { map.clear_gamepad(); });
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Starts a new game with the menu settings from any state. `NewGame` rolls a new seed while
+/// `RestartGame` plays the same board again, which also covers hand-made boards.
+pub(crate) fn check_new_game(query: Query<&ActionState<GameInteractions>>,
+                             mut ui_state: ResMut<UiState>,
+                             current: Option<Res<FieldGenerationOptions>>,
+                             rebinding: Res<Rebinding>,
+                             mut egui_ctx: ResMut<EguiContext>,
+                             mut commands: Commands) {
+    // typing a seed must not start a game
+    if rebinding.is_capturing() || egui_ctx.ctx_mut().wants_keyboard_input() {
+        return;
+    }
+    let action = query.single();
+    let restart = action.just_pressed(GameInteractions::RestartGame);
+    if action.just_pressed(GameInteractions::NewGame) && !restart {
+        ui_state.seed = fastrand::u64(0..u64::MAX).to_string();
+    }
+    let puzzle = current.filter(|options| options.puzzle.is_some());
+    if let Some(puzzle) = puzzle.filter(|_| restart) {
+        commands.insert_resource::<FieldGenerationOptions>(puzzle.clone());
+        commands.insert_resource(NextState(GameState::Playing));
+    } else if restart || action.just_pressed(GameInteractions::NewGame) {
+        commands.insert_resource::<FieldGenerationOptions>(ui_state.clone().into());
+        commands.insert_resource(NextState(GameState::Playing));
+    }
+}
+
+pub(crate) fn check_game_status(field: Res<Field>, mut outcomes: EventWriter<GameOutcome>) {
+    if !field.is_changed() {
+        return;
+    }
+    match field.status() {
+        GameStatus::Won => outcomes.send(GameOutcome::Won),
+        GameStatus::Lost { .. } => outcomes.send(GameOutcome::Lost),
+        GameStatus::GaveUp => outcomes.send(GameOutcome::GaveUp),
+        GameStatus::NotStarted | GameStatus::InProgress => {}
+    }
+}
+
+pub(crate) fn finish_game(mut events: EventReader<GameOutcome>, mut stats: ResMut<GameStats>, mut sounds: EventWriter<SoundEffect>, mut commands: Commands) {
+    if let Some(outcome) = events.iter().last() {
+        let title = match outcome {
+            GameOutcome::Won => {
+                sounds.send(SoundEffect::Victory);
+                stats.won += 1;
+                TitleText("You won!".to_owned(), Color::GREEN)
+            }
+            GameOutcome::Lost => {
+                sounds.send(SoundEffect::Explosion);
+                stats.lost += 1;
+                TitleText("You lost!".to_owned(), Color::RED)
+            }
+            GameOutcome::GaveUp => {
+                stats.lost += 1;
+                TitleText("You gave up!".to_owned(), Color::ORANGE)
+            }
+        };
+        commands.insert_resource(title);
+        commands.insert_resource(NextState(GameState::GameOver));
+    }
+}
+
+pub(crate) fn check_game_actions(query: Query<&ActionState<GameInteractions>>, mut commands: Commands) {
+    let action = query.single();
+    if action.just_pressed(GameInteractions::OpenMenu) {
+        commands.insert_resource(NextState(GameState::Paused));
+    }
+}
+
+
+/// Reveals `cell` and the safe area around it.
+pub(crate) fn reveal_cell(field: &mut Field, cell: CellHandle) -> RevealResult {
+    let result = field.reveal(cell);
+    if let RevealResult::Empty(_) = result {
+        RevealerImpl::reveal_area(field, cell);
+    }
+    result
+}
+
+/// Cycles the mark on `cell` and plays the flag sound if anything changed.
+pub(crate) fn toggle_mark(field: &mut Field, cell: CellHandle, settings: &Settings, sounds: &mut EventWriter<SoundEffect>) {
+    let before = field[cell].get_state();
+    field.toggle_mark(cell, settings.question_marks);
+    if field[cell].get_state() != before {
+        sounds.send(SoundEffect::Flag);
+    }
+}
 
 pub(crate) fn update_cell_interaction(mut query: Query<&ActionState<FieldInteraction>>,
                            mut field: ResMut<Field>,
                            cam_options: Res<CameraOptions>,
                            converter: Res<MousePositionToCellConverter>,
+                           cursor: Res<CellCursor>,
+                           touch: Res<TouchTracker>,
+                           settings: Res<Settings>,
+                           mut origin: ResMut<RippleOrigin>,
+                           mut sounds: EventWriter<SoundEffect>,
+                           time: Res<Time>,
                            cam_query: Query<(&Camera, &GlobalTransform), With<MainCam>>,
-                           windows: Res<Windows>,
-                           mut commands: Commands) {
+                           windows: Res<Windows>) {
     let action = query.single_mut();
+    // browsers may emulate mouse clicks for taps, those are handled by the touch systems already
+    if touch.recently_touched(time.seconds_since_startup()) && !cursor.active {
+        return;
+    }
+
+    if action.just_pressed(FieldInteraction::RevealAll) {
+        field.reveal_all();
+        return;
+    }
 
     let (cam, trans) = cam_query.single();
-    if let Some(cell) = converter.get_cell_coords(cam, trans, windows, &cam_options, &field) {
+    let cell = if cursor.active {
+        Some(cursor.handle)
+    } else {
+        converter.get_cell_coords(cam, trans, windows, &cam_options, &field)
+    };
+    if let Some(cell) = cell {
+        if action.just_pressed(FieldInteraction::Reveal) || action.just_pressed(FieldInteraction::Chord) {
+            origin.0 = Some(cell);
+        }
         if action.just_pressed(FieldInteraction::Reveal) {
-            match field.reveal(cell) {
-                RevealResult::Mine => {
-                    commands.insert_resource(TitleText("You lost!".to_owned(), Color::RED));
-                    commands.insert_resource(NextState(GameState::Menu));
-                }
-                RevealResult::Empty(_) => {
-                    RevealerImpl::reveal_area(&mut field, cell);
-                }
-                RevealResult::AlreadyRevealed => {}
+            if let Some(sound) = SoundEffect::for_reveal(&reveal_cell(&mut field, cell)) {
+                sounds.send(sound);
+            }
+        }
+        if action.just_pressed(FieldInteraction::Chord) {
+            if !matches!(RevealerImpl::chord(&mut field, cell), RevealResult::AlreadyRevealed) {
+                sounds.send(SoundEffect::Chord);
             }
         }
         if action.just_pressed(FieldInteraction::ToggleMark) {
             info!("Toggling mark");
-            field.toggle_mark(cell);
+            toggle_mark(&mut field, cell, &settings, &mut sounds);
         }
     }
 }
@@ -67,18 +233,29 @@ impl MousePositionToCellConverter {
         cell
     }
 
-    pub(crate) fn get_cell_coords(&self, cam: &Camera, trans: &GlobalTransform, windows: Res<Windows>, cam_options: &CameraOptions, field: &Field) -> Option<CellHandle> {
-        let wnd = if let RenderTarget::Window(wnd) = &cam.target {
+    pub(crate) fn get_window<'a>(&self, cam: &Camera, windows: &'a Windows) -> &'a Window {
+        if let RenderTarget::Window(wnd) = &cam.target {
             windows.get(wnd.clone()).unwrap()
         } else {
             windows.primary()
-        };
-        if let Some(cur_pos) = wnd.cursor_position() {
-            let window_size = Vec2::new(wnd.width(), wnd.height());
-            let ndc = (cur_pos / window_size) * 2.0 - Vec2::ONE;
-            let cell = self.screen_to_cell(ndc, &(trans.compute_matrix() * cam.projection_matrix.inverse()), cam_options, field);
-            return Some(cell);
         }
-        None
+    }
+
+    /// Converts a position in window coordinates (origin bottom left) to the cell below it.
+    pub(crate) fn get_cell_at(&self, cam: &Camera, trans: &GlobalTransform, wnd: &Window, position: Vec2, cam_options: &CameraOptions, field: &Field) -> Option<CellHandle> {
+        let window_size = Vec2::new(wnd.width(), wnd.height());
+        let ndc = (position / window_size) * 2.0 - Vec2::ONE;
+        let cell = self.screen_to_cell(ndc, &(trans.compute_matrix() * cam.projection_matrix.inverse()), cam_options, field);
+        if cell.x < field.width && cell.y < field.height {
+            Some(cell)
+        } else {
+            None
+        }
+    }
+
+    pub(crate) fn get_cell_coords(&self, cam: &Camera, trans: &GlobalTransform, windows: Res<Windows>, cam_options: &CameraOptions, field: &Field) -> Option<CellHandle> {
+        let wnd = self.get_window(cam, &windows);
+        wnd.cursor_position()
+            .and_then(|cur_pos| self.get_cell_at(cam, trans, wnd, cur_pos, cam_options, field))
     }
 }
@@ -1,7 +1,6 @@
-use std::io;
 use std::collections::HashMap;
-use std::time::Duration;
 use bevy::prelude::*;
+use bevy::asset::AssetServerSettings;
 use bevy::asset::diagnostic::AssetCountDiagnosticsPlugin;
 use bevy::diagnostic::{EntityCountDiagnosticsPlugin, FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin};
 
@@ -9,133 +8,91 @@ use bevy::render::camera::{CameraProjection, RenderTarget, ScalingMode, WindowOr
 
 use bevy::sprite::{MaterialMesh2dBundle, SpecializedMaterial2d};
 use bevy::text::{Text2dBounds};
-use bevy::window::WindowMode;
-use bevy_egui::EguiPlugin;
+use bevy_egui::{EguiPlugin, EguiSystem};
 use iyes_loopless::state::NextState;
 use iyes_loopless::prelude::*;
 
 use leafwing_input_manager::InputManagerBundle;
-use leafwing_input_manager::prelude::{InputManagerPlugin, InputMap};
-use leafwing_input_manager::user_input::InputButton;
+use leafwing_input_manager::prelude::InputManagerPlugin;
 
 
 use crate::field::{CellHandle, CellState, Field, Mark, RevealResult};
 
 
-use crate::generate::{FieldGenerationOptions, DefaultFieldGenerator};
+use crate::generate::{BoardSource, FieldGenerationOptions, GeneratorRegistry, STABLE_GENERATOR};
 use crate::interactions::{FieldInteraction, GameInteractions, MousePositionToCellConverter, update_cell_interaction};
 use crate::revealing::{Revealer, RevealerImpl};
 
-use serde::{Deserialize, Serialize};
-use crate::menu::{init_seed, init_visuals, Overlay, show_overlay, TitleText, UiState};
+use crate::camera::{apply_camera_view, CameraView, reset_camera_view};
+use crate::cursor::{CellCursor, init_cursor, update_cursor, update_cursor_highlight, update_zoom};
+use crate::interactions::{assign_gamepad, check_game_actions, check_game_status, check_new_game, finish_game, GameOutcome, GameStats};
+use crate::controls::{apply_controls, capture_binding, ControlsConfig, Rebinding, show_controls};
+use crate::theme::{apply_theme, Theme, ThemeLoader, Themes};
+use crate::touch::{show_touch_controls, TouchTracker, update_touch};
+use crate::menu::{check_menu_actions, dismiss_game_over, forward_gamepad_to_egui, init_seed, init_visuals, MenuFocus, MenuScreen, Overlay, show_game_over, show_hud, show_overlay, TitleText, UiState};
+use crate::animation::{animate_cells, RippleOrigin};
+use crate::hover::{apply_hover, clear_hover, Hover, update_hover};
 use crate::render::update_render;
+use crate::settings::{persist_settings, Settings, show_settings};
+use crate::sound::{play_sounds, SoundEffect, Sounds};
+use crate::pause::{GameTimer, pause_on_focus_loss, reset_timer, resume_game, show_pause, tick_timer};
+use crate::window::{apply_window_config, WindowConfig};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::window::persist_window_config;
+use crate::skin::{ActiveSkin, prepare_skin, Skins, Tile};
+use crate::editor::{check_editor_actions, EditorState, enter_editor, leave_editor, paint_board, show_editor, update_editor_field};
+use crate::puzzle::{LevelPack, LevelPackLoader, LevelPacks, LevelProgress, PuzzleBoard, PuzzleBoardLoader, PuzzleGenerator, record_puzzle_progress, show_puzzle_message, show_puzzles};
 
 mod field;
 mod generate;
+mod constraints;
+mod seed;
 mod revealing;
 mod interactions;
 mod render;
+mod animation;
+mod hover;
 mod menu;
+mod cursor;
+mod camera;
+mod config;
+mod controls;
+mod touch;
+mod theme;
+mod skin;
+mod settings;
+mod sound;
+mod window;
+mod pause;
+mod puzzle;
+mod editor;
 
 #[derive(PartialEq, Debug, Clone, Copy, Hash, Eq)]
 pub enum GameState {
     Playing,
+    Paused,
+    GameOver,
     Menu,
+    Editor,
     Init,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
-struct WindowConfig {
-    width: f32,
-    height: f32,
-    title: String,
-    mode: WindowModeConfig,
-    transparent: bool,
-    resizable: bool,
-    decorations: bool,
-}
-
-impl From<WindowDescriptor> for WindowConfig {
-    fn from(descriptor: WindowDescriptor) -> Self {
-        Self {
-            width: descriptor.width,
-            height: descriptor.height,
-            title: descriptor.title,
-            mode: descriptor.mode.into(),
-            transparent: descriptor.transparent,
-            resizable: descriptor.resizable,
-            decorations: descriptor.decorations,
-        }
-    }
-}
-
-impl TryFrom<WindowConfig> for WindowDescriptor {
-    type Error = io::Error;
-
-    fn try_from(value: WindowConfig) -> Result<Self, Self::Error> {
-        Ok(Self {
-            width: value.width,
-            height: value.height,
-            title: value.title,
-            mode: value.mode.try_into()?,
-            transparent: value.transparent,
-            resizable: value.resizable,
-            decorations: value.decorations,
-            ..default()
-        })
-    }
-}
+fn main() {
+    let mut app = App::new();
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
-struct WindowModeConfig(String);
+    // logging only starts with the default plugins, so a broken file is reported further down
+    let (window_config, window_error) = WindowConfig::load_or_default();
+    app.insert_resource(WindowDescriptor::try_from(window_config.clone()).expect("window mode is checked when the config is loaded"));
+    app.insert_resource(window_config);
 
-impl From<WindowMode> for WindowModeConfig {
-    fn from(mode: WindowMode) -> Self {
-        match mode {
-            WindowMode::Windowed => WindowModeConfig("Windowed".to_owned()),
-            WindowMode::BorderlessFullscreen => WindowModeConfig("BorderlessFullscreen".to_owned()),
-            WindowMode::SizedFullscreen => WindowModeConfig("SizedFullscreen".to_owned()),
-            WindowMode::Fullscreen => WindowModeConfig("Fullscreen".to_owned()),
-        }
-    }
-}
-
-impl TryFrom<WindowModeConfig> for WindowMode {
-    type Error = io::Error;
 
-    fn try_from(value: WindowModeConfig) -> Result<Self, Self::Error> {
-        match value.0.as_str() {
-            "Windowed" => Ok(WindowMode::Windowed),
-            "BorderlessFullscreen" => Ok(WindowMode::BorderlessFullscreen),
-            "SizedFullscreen" => Ok(WindowMode::SizedFullscreen),
-            "Fullscreen" => Ok(WindowMode::Fullscreen),
-            _ => Err(io::Error::new(io::ErrorKind::InvalidInput, "Invalid window mode")),
-        }
-    }
-}
-
-
-fn main() {
-    let mut app = App::new();
-    // #[cfg(not(target_arch = "wasm32"))]
-    //     let config = ConfigBuilder::new()
-    //     .folder("./config", None)
-    //     .build();
-    // #[cfg(target_arch = "wasm32")]
-    // let config = ConfigBuilder::new()
-    //     .http("http://localhost:3000/config/window.ron")
-    //     .build();
-
-    // println!("{:?}", config);
-
-    app.insert_resource(WindowDescriptor{
-        height: 800.0,
-        width: 800.0,
-        title: "Minesweeper".to_owned(),
+    // lets artists tweak themes while the game is running
+    #[cfg(not(target_arch = "wasm32"))]
+    app.insert_resource(AssetServerSettings {
+        watch_for_changes: true,
         ..default()
     });
 
-
     app.add_plugins(DefaultPlugins)
         .add_plugin(FrameTimeDiagnosticsPlugin::default())
         .add_plugin(LogDiagnosticsPlugin::default())
@@ -143,7 +100,17 @@ fn main() {
         .add_plugin(AssetCountDiagnosticsPlugin::<Image>::default())
         .add_plugin(InputManagerPlugin::<FieldInteraction>::default())
         .add_plugin(InputManagerPlugin::<GameInteractions>::default())
-        .add_plugin(EguiPlugin);
+        .add_plugin(EguiPlugin)
+        .add_asset::<Theme>()
+        .init_asset_loader::<ThemeLoader>()
+        .add_asset::<PuzzleBoard>()
+        .init_asset_loader::<PuzzleBoardLoader>()
+        .add_asset::<LevelPack>()
+        .init_asset_loader::<LevelPackLoader>();
+
+    if let Some(e) = window_error {
+        error!("Could not load config/window.ron, using the default window: {}", e);
+    }
 
     let field_option = Some(FieldGenerationOptions {
         width: 20,
@@ -154,7 +121,24 @@ fn main() {
 
 
     app.insert_resource(InitState::default());
+    app.insert_resource(CellCursor::default());
+    app.insert_resource(CameraView::default());
+    app.insert_resource(MenuFocus::default());
+    app.insert_resource(MenuScreen::default());
+    app.insert_resource(Rebinding::default());
+    app.insert_resource(TouchTracker::default());
+    app.insert_resource(GameStats::default());
+    app.insert_resource(ActiveSkin::default());
+    app.insert_resource(Settings::load_or_default());
+    app.insert_resource(LevelProgress::load_or_default());
+    app.insert_resource(EditorState::default());
+    app.insert_resource(RippleOrigin::default());
+    app.insert_resource(Hover::default());
+    app.insert_resource(GameTimer::default());
+    app.add_event::<GameOutcome>();
+    app.add_event::<SoundEffect>();
     app.insert_resource(field_option);
+    app.insert_resource(GeneratorRegistry::default());
     app.insert_resource(ClearColor(Color::rgb(0.9, 0.9, 0.9)));
     app.insert_resource(CameraOptions {
         scale: 1000.0,
@@ -167,16 +151,12 @@ fn main() {
        columns: 20,
        rows: 20,
        mines: 20,
+       generator: STABLE_GENERATOR.to_owned(),
+       constraints: default(),
+       code: String::new(),
+       code_error: None,
     });
 
-    let mut fixedupdate = SystemStage::parallel();
-
-
-
-    fixedupdate
-        .add_system(update_render.run_if_resource_exists::<Field>());
-
-
 
     app.add_loopless_state(GameState::Init)
         .add_enter_system(GameState::Init, init_visuals)
@@ -184,23 +164,96 @@ fn main() {
         .add_enter_system(GameState::Init, init_camera)
         .add_enter_system(GameState::Init, init_input)
         .add_enter_system(GameState::Menu, init_seed)
-        .add_enter_system(GameState::Playing, init_render_field);
+        .add_enter_system(GameState::Playing, init_render_field)
+        .add_enter_system(GameState::Playing, init_cursor)
+        .add_enter_system(GameState::Playing, reset_camera_view)
+        .add_enter_system(GameState::Playing, reset_timer)
+        .add_enter_system(GameState::Editor, enter_editor);
 
     app.add_exit_system(GameState::Menu, despawn_with::<Overlay>);
-
-    app.add_stage_before(CoreStage::Update, "FixedUpdate", FixedTimestepStage::from_stage(Duration::from_millis(125), fixedupdate));
+    app.add_exit_system(GameState::Playing, clear_hover);
+    app.add_exit_system(GameState::Editor, leave_editor);
 
     app.add_system_set(ConditionSet::new().run_in_state(GameState::Init)
         .with_system(check_init).into());
 
-    app.add_system_set(ConditionSet::new().run_in_state(GameState::Playing)
+    // a puzzle that isn't loaded sends the game back to the menu before a board exists, the
+    // systems of the game wait for the board
+    app.add_system_set(ConditionSet::new().run_in_state(GameState::Playing).run_if_resource_exists::<Field>().label("cursor")
+                           .with_system(update_cursor).into());
+
+    app.add_system_set(ConditionSet::new().run_in_state(GameState::Playing).run_if_resource_exists::<Field>().label("interactions").after("cursor")
+                           .with_system(update_cursor_highlight)
+                           .with_system(update_hover)
+                           .with_system(update_zoom)
                            .with_system(update_cell_interaction)
-                           .with_system(check_victory).into());
+                           .with_system(update_touch)
+                           .with_system(show_touch_controls)
+                           .with_system(show_hud)
+                           .with_system(check_game_actions)
+                           .with_system(pause_on_focus_loss)
+                           .with_system(show_puzzle_message)
+                           .with_system(tick_timer).into());
+
+    app.add_system_set(ConditionSet::new().run_in_state(GameState::Playing).run_if_resource_exists::<Field>().label("status").after("interactions")
+                           .with_system(check_game_status).into());
+
+    app.add_system_set(ConditionSet::new().run_in_state(GameState::Playing).run_if_resource_exists::<Field>().after("status")
+                           .with_system(finish_game)
+                           .with_system(record_puzzle_progress).into());
+
+    app.add_system_set(ConditionSet::new().run_in_state(GameState::Paused)
+                           .with_system(show_pause)
+                           .with_system(resume_game).into());
+
+    app.add_system_set(ConditionSet::new().run_in_state(GameState::GameOver)
+                           .with_system(show_game_over)
+                           .with_system(show_hud)
+                           .with_system(dismiss_game_over).into());
+
+    app.add_system_set(ConditionSet::new().run_not_in_state(GameState::Init)
+                           .with_system(check_new_game).into());
 
     app.add_system_set(ConditionSet::new().run_in_state(GameState::Menu).with_system(show_overlay).into());
 
-    // app.add_system_set(ConditionSet::new().run_in_state(GameState::Menu)
-    //     .with_system(check_menu_actions).into());
+    app.add_system_set(ConditionSet::new().run_in_state(GameState::Menu)
+        .with_system(check_menu_actions)
+        .with_system(show_controls)
+        .with_system(show_settings)
+        .with_system(show_puzzles)
+        .with_system(capture_binding)
+        .with_system(apply_controls).into());
+
+    app.add_system_set(ConditionSet::new().run_in_state(GameState::Editor).label("editor")
+        .with_system(show_editor)
+        .with_system(paint_board)
+        .with_system(check_editor_actions).into());
+
+    app.add_system_set(ConditionSet::new().run_in_state(GameState::Editor).after("editor")
+        .with_system(update_editor_field).into());
+
+    // egui only sees keys that are added between reading the input and starting the frame
+    app.add_system_to_stage(CoreStage::PreUpdate, forward_gamepad_to_egui
+        .run_if_resource_exists::<ControlsConfig>()
+        .after(EguiSystem::ProcessInput)
+        .before(EguiSystem::BeginFrame));
+
+    app.add_system(assign_gamepad);
+    app.add_system(apply_camera_view);
+    // the browser owns the size of the canvas, there is no file to write it to
+    #[cfg(not(target_arch = "wasm32"))]
+    app.add_system(persist_window_config);
+    app.add_system(apply_window_config);
+    app.add_system(persist_settings.run_if_resource_exists::<Skins>());
+    app.add_system(apply_theme.run_if_resource_exists::<Themes>().label("appearance"));
+    app.add_system(prepare_skin.run_if_resource_exists::<Skins>().label("appearance"));
+    // the new cells have to exist before the renderer looks for them
+    app.add_system_to_stage(CoreStage::PreUpdate, respawn_cells.run_if_resource_exists::<Field>());
+    // cells are redrawn in the frame the field changed, right after the systems that changed it
+    app.add_system(update_render.run_if_resource_exists::<Field>().label("render").after("status").after("appearance"));
+    app.add_system(animate_cells.after("render"));
+    app.add_system(play_sounds.run_if_resource_exists::<Sounds>());
+    app.add_system(apply_hover.run_if_resource_exists::<Field>().after("render"));
 
     app.run();
 }
@@ -210,6 +263,10 @@ struct CellComponent {
     pub handle: CellHandle,
 }
 
+/// Entity that draws each cell of the current board, so changes can go straight to the right entity.
+#[derive(Debug, Default, Deref, DerefMut)]
+struct CellEntities(HashMap<CellHandle, Entity>);
+
 #[derive(Bundle)]
 struct CellBundle<M: SpecializedMaterial2d> {
     cell: CellComponent,
@@ -284,9 +341,11 @@ impl CellOptions {
 struct StateMaterials {
     revealed: RevealedColor,
     hidden: HiddenColor,
+    hover: HoverColor,
     mine: MineColor,
+    exploded: ExplodedColor,
     marked_mine: MarkedMineColor,
-    marked_empty: MarkedEmptyColor,
+    question: QuestionColor,
     colors_numbers: HashMap<u8, Color>,
 }
 
@@ -302,11 +361,14 @@ struct RevealedColor(Color);
 #[derive(Deref, DerefMut)]
 struct MineColor(Color);
 
+#[derive(Deref, DerefMut)]
+struct ExplodedColor(Color);
+
 #[derive(Deref, DerefMut)]
 struct MarkedMineColor(Color);
 
 #[derive(Deref, DerefMut)]
-struct MarkedEmptyColor(Color);
+struct QuestionColor(Color);
 
 #[derive(Debug, Component)]
 struct MainCam;
@@ -360,63 +422,110 @@ fn init_camera(mut commands: Commands, camera_options: Res<CameraOptions>, mut i
     init_state.camera = true;
 }
 
-fn init_resources(mut commands: Commands, asset_server: ResMut<AssetServer>, mut init_state: ResMut<InitState>) {
-    let color_hidden = Color::rgb(0.4, 0.4, 0.4);
-    let color_revealed = Color::rgb(0.8, 0.8, 0.8);
-    let color_mine = Color::rgb(0.8, 0.0, 0.0);
-    let color_marked_mine = Color::rgb(0.6, 0.1, 0.1);
-    let color_marked_empty = Color::rgb(0.1, 0.6, 0.1);
-
-    let state_materials = StateMaterials {
-        revealed: RevealedColor(color_revealed),
-        hidden: HiddenColor(color_hidden),
-        mine: MineColor(color_mine),
-        marked_mine: MarkedMineColor(color_marked_mine),
-        marked_empty: MarkedEmptyColor(color_marked_empty),
-        colors_numbers: HashMap::from([
-            (1 as u8, Color::rgb(0.0, 0.0, 1.0)),
-            (2 as u8, Color::rgb(0.0, 1.0, 0.0)),
-            (3 as u8, Color::rgb(0.0, 1.0, 1.0)),
-            (4 as u8, Color::rgb(1.0, 0.0, 0.0)),
-            (5 as u8, Color::rgb(1.0, 0.0, 1.0)),
-            (6 as u8, Color::rgb(1.0, 1.0, 0.0)),
-            (7 as u8, Color::rgb(1.0, 1.0, 1.0)),
-            (8 as u8, Color::rgb(0.5, 0.5, 0.5))
-        ]),
-    };
-
+fn init_resources(mut commands: Commands, asset_server: ResMut<AssetServer>, settings: Res<Settings>, mut init_state: ResMut<InitState>) {
+    let state_materials = StateMaterials::from(&Theme::default());
     commands.insert_resource(state_materials);
+    commands.insert_resource(Themes::load(&asset_server, &settings.theme));
+    commands.insert_resource(Skins::load(&asset_server, settings.skin.as_deref()));
+    commands.insert_resource(Sounds::load(&asset_server));
+    commands.insert_resource(LevelPacks::load(&asset_server));
     let font: Handle<Font> = asset_server.load("fonts\\pixelated_arial_regular_11.ttf");
     commands.insert_resource(TextFont(font));
     init_state.resources = true;
 }
 
 fn init_input(mut commands: Commands, mut init_state: ResMut<InitState>) {
-    let mut input_map = InputMap::default();
-    input_map.insert(FieldInteraction::Reveal, InputButton::Mouse(MouseButton::Left));
-    input_map.insert(FieldInteraction::ToggleMark, InputButton::Mouse(MouseButton::Right));
+    let controls = ControlsConfig::load_or_default();
     commands.spawn_bundle(InputManagerBundle {
-        input_map,
+        input_map: controls.field_input_map(),
         ..Default::default()
     });
+    commands.spawn_bundle(InputManagerBundle {
+        input_map: controls.game_input_map(),
+        ..Default::default()
+    });
+    commands.insert_resource(controls);
     init_state.input = true;
 }
 
 fn init_render_field(mut commands: Commands, field_options: Res<FieldGenerationOptions>, mut materials: ResMut<Assets<ColorMaterial>>, mut meshes: ResMut<Assets<Mesh>>,
-                     camera_options: Res<CameraOptions>, state_materials: Res<StateMaterials>,
+                     camera_options: Res<CameraOptions>, state_materials: Res<StateMaterials>, skin: Res<ActiveSkin>,
+                     generators: Res<GeneratorRegistry>,
+                     (packs, pack_assets, boards): (Res<LevelPacks>, Res<Assets<LevelPack>>, Res<Assets<PuzzleBoard>>),
                      old: Query<Entity, With<CellComponent>>) {
+    // every new game inserts its options, resuming from the pause screen keeps the board
+    if !field_options.is_changed() {
+        return;
+    }
+    let mut source: Box<dyn BoardSource> = match field_options.puzzle {
+        Some(puzzle) => match packs.board(puzzle, &pack_assets, &boards) {
+            Some(board) => Box::new(PuzzleGenerator::new(board)),
+            None => {
+                // a random board in place of the puzzle would only confuse the player
+                error!("Puzzle {} of pack {} is not loaded", puzzle.level + 1, puzzle.pack + 1);
+                commands.insert_resource(TitleText("Puzzle not loaded".to_string(), Color::RED));
+                commands.insert_resource(NextState(GameState::Menu));
+                return;
+            }
+        },
+        None => Box::new(generators.generator(&field_options)),
+    };
     for old in old.iter() {
         commands.entity(old).despawn_recursive();
     }
 
-    let field = DefaultFieldGenerator::generate(Some(field_options.clone()));
+    let field = source.generate(&field_options);
 
 
     let converter = MousePositionToCellConverter::new();
     commands.insert_resource(converter);
 
-    let cell_options = CellOptions::from_field_dimension_and_camera_options((field.width, field.height), &camera_options)
-        .with_padding(1.0);
+    spawn_cells(&mut commands, &field, &camera_options, &skin, &state_materials, &mut materials, &mut meshes);
+
+    commands.insert_resource(field);
+}
+
+/// Rebuilds the cells of the current board when the skin switches between sprites and flat colours.
+fn respawn_cells(mut commands: Commands, mut field: ResMut<Field>, mut materials: ResMut<Assets<ColorMaterial>>, mut meshes: ResMut<Assets<Mesh>>,
+                 camera_options: Res<CameraOptions>, state_materials: Res<StateMaterials>, skin: Res<ActiveSkin>,
+                 old: Query<Entity, With<CellComponent>>) {
+    if !skin.is_changed() || skin.is_added() {
+        return;
+    }
+    for old in old.iter() {
+        commands.entity(old).despawn_recursive();
+    }
+    spawn_cells(&mut commands, &field, &camera_options, &skin, &state_materials, &mut materials, &mut meshes);
+    field.mark_all_changed();
+}
+
+fn spawn_cells(commands: &mut Commands, field: &Field, camera_options: &CameraOptions, skin: &ActiveSkin, state_materials: &StateMaterials,
+               materials: &mut Assets<ColorMaterial>, meshes: &mut Assets<Mesh>) {
+    let cell_options = CellOptions::from_field_dimension_and_camera_options((field.width, field.height), camera_options);
+    let mut entities = CellEntities::default();
+
+    if let Some(atlas) = &skin.0 {
+        // tiles of a skin bring their own borders, so they are drawn without a gap
+        for cell in field.get_handles() {
+            let entity = commands.spawn_bundle(SpriteSheetBundle {
+                sprite: TextureAtlasSprite {
+                    index: Tile::Hidden.index(),
+                    custom_size: Some(Vec2::new(cell_options.width, cell_options.height)),
+                    ..default()
+                },
+                texture_atlas: atlas.clone(),
+                transform: Transform::default().with_translation(cell_options.get_translation(cell)),
+                ..default()
+            }).insert(CellComponent {
+                handle: cell,
+            }).id();
+            entities.insert(cell, entity);
+        }
+        commands.insert_resource(entities);
+        return;
+    }
+
+    let cell_options = cell_options.with_padding(1.0);
 
     let m = Mesh::from(shape::Quad::new(Vec2::new(cell_options.width, cell_options.height)));
 
@@ -439,18 +548,7 @@ fn init_render_field(mut commands: Commands, field_options: Res<FieldGenerationO
             mesh: mb,
 
         };
-        commands.spawn_bundle(bundle);
+        entities.insert(cell, commands.spawn_bundle(bundle).id());
     };
-
-    commands.insert_resource(field);
-}
-
-
-fn check_victory(field: Res<Field>, mut commands: Commands) {
-    if field.is_won() {
-        commands.insert_resource(TitleText("You won!".to_string(), Color::GREEN));
-        commands.insert_resource(NextState(GameState::Menu));
-
-    }
+    commands.insert_resource(entities);
 }
-
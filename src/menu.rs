@@ -1,11 +1,21 @@
-use std::collections::hash_map::DefaultHasher;
-use std::hash::{Hash, Hasher};
 use bevy::prelude::*;
-use bevy_egui::{egui, EguiContext};
-use bevy_egui::egui::{Align2, Color32, FontData, FontDefinitions, FontFamily, Frame, Grid, Rgba, RichText, Slider};
+use bevy::utils::HashMap;
+use bevy::window::WindowId;
+use bevy_egui::{egui, EguiContext, EguiInput};
+use bevy_egui::egui::{Align2, Color32, ComboBox, FontData, FontDefinitions, FontFamily, Frame, Grid, Rgba, RichText, Slider, TextEdit};
 use bevy_egui::egui::style::Margin;
-use iyes_loopless::state::NextState;
+use iyes_loopless::state::{CurrentState, NextState};
+use leafwing_input_manager::prelude::ActionState;
 use crate::{FieldGenerationOptions, GameState};
+use crate::controls::{Binding, ControlsConfig, Rebinding};
+use crate::constraints::GenerationConstraints;
+use crate::generate::GeneratorRegistry;
+use crate::interactions::{FieldInteraction, GameInteractions, GameStats};
+use crate::field::Field;
+use crate::pause::GameTimer;
+use crate::seed::{GameCode, MAX_BOARD_SIZE, MAX_MINE_COUNT, seed_from_str};
+use crate::skin::Skins;
+use crate::theme::{Theme, Themes};
 
 #[derive(Clone)]
 pub struct UiState {
@@ -13,27 +23,82 @@ pub struct UiState {
     pub rows: usize,
     pub columns: usize,
     pub seed: String,
+    pub generator: String,
+    pub constraints: GenerationConstraints,
+    /// Text of the game code field, follows the other fields while it isn't being edited.
+    pub code: String,
+    /// Why the edited game code can't be played.
+    pub code_error: Option<String>,
+}
+
+impl UiState {
+    pub fn game_code(&self) -> GameCode {
+        GameCode::from(&FieldGenerationOptions::from(self.clone()))
+    }
+
+    /// Takes over the board described by `code`. The seed is shown as a number so it maps to the
+    /// same value again. Codes don't carry generation constraints, so these are cleared.
+    pub fn apply_code(&mut self, code: GameCode) {
+        self.constraints = GenerationConstraints::default();
+        self.columns = code.width;
+        self.rows = code.height;
+        self.mines = code.mine_count;
+        self.seed = code.seed.to_string();
+        self.generator = code.generator;
+    }
 }
 
 impl From<UiState> for FieldGenerationOptions {
     fn from(o: UiState) -> Self {
-        let mut hasher = DefaultHasher::new();
-        o.seed.hash(&mut hasher);
-        let seed = hasher.finish();
         FieldGenerationOptions {
             mine_count: o.mines,
             height: o.rows,
             width: o.columns,
-            seed,
+            seed: seed_from_str(&o.seed),
+            generator: o.generator,
+            constraints: o.constraints,
+            puzzle: None,
         }
     }
 }
 
 
-
 #[derive(Component, Debug, Default)]
 pub(crate) struct Overlay;
 
+/// Page of the menu that is currently shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum MenuScreen {
+    Main,
+    Settings,
+    Controls,
+    Puzzles,
+}
+
+impl Default for MenuScreen {
+    fn default() -> Self {
+        MenuScreen::Main
+    }
+}
+
+/// Row of the overlay selected with the keyboard or a controller.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct MenuFocus(pub usize);
+
+impl MenuFocus {
+    const MINES: usize = 0;
+    const ROWS: usize = 1;
+    const COLUMNS: usize = 2;
+    const THEME: usize = 3;
+    const SKIN: usize = 4;
+    const GENERATOR: usize = 5;
+    const NEW_GAME: usize = 6;
+    const PUZZLES: usize = 7;
+    const SETTINGS: usize = 8;
+    const CONTROLS: usize = 9;
+    const COUNT: usize = 10;
+}
+
 pub(crate) struct TitleText(pub String, pub Color);
 
 pub(crate) fn init_visuals(mut egui_ctx: ResMut<EguiContext>) {
@@ -57,7 +122,211 @@ pub(crate) fn init_seed(mut ui_state: ResMut<UiState>) {
     ui_state.seed = fastrand::u64(0..u64::MAX).to_string();
 }
 
-pub(crate) fn show_overlay(mut commands: Commands, txt: Res<TitleText>, mut egui_ctx: ResMut<EguiContext>, mut ui_state: ResMut<UiState>) {
+pub(crate) fn check_menu_actions(mut commands: Commands,
+                                 query: Query<&ActionState<GameInteractions>>,
+                                 mut egui_ctx: ResMut<EguiContext>,
+                                 mut focus: ResMut<MenuFocus>,
+                                 mut ui_state: ResMut<UiState>,
+                                 mut themes: ResMut<Themes>,
+                                 mut skins: ResMut<Skins>,
+                                 generators: Res<GeneratorRegistry>,
+                                 mut screen: ResMut<MenuScreen>,
+                                 rebinding: Res<Rebinding>) {
+    // the select that left another page must not also pick a row here
+    if *screen != MenuScreen::Main || screen.is_changed() || rebinding.is_capturing() {
+        return;
+    }
+    let action = query.single();
+
+    // leave the keys to egui while the seed is being edited
+    if egui_ctx.ctx_mut().wants_keyboard_input() {
+        return;
+    }
+    if action.just_pressed(GameInteractions::MenuUp) {
+        focus.0 = (focus.0 + MenuFocus::COUNT - 1) % MenuFocus::COUNT;
+    }
+    if action.just_pressed(GameInteractions::MenuDown) {
+        focus.0 = (focus.0 + 1) % MenuFocus::COUNT;
+    }
+
+    let mut delta: isize = 0;
+    if action.just_pressed(GameInteractions::MenuLeft) {
+        delta -= 1;
+    }
+    if action.just_pressed(GameInteractions::MenuRight) {
+        delta += 1;
+    }
+    if delta != 0 && focus.0 == MenuFocus::THEME {
+        let count = themes.handles.len() as isize;
+        themes.selected = ((themes.selected as isize + delta + count) % count) as usize;
+    } else if delta != 0 && focus.0 == MenuFocus::SKIN {
+        skins.cycle(delta);
+    } else if delta != 0 && focus.0 == MenuFocus::GENERATOR {
+        ui_state.generator = generators.cycle(&ui_state.generator, delta).to_owned();
+    } else if delta != 0 {
+        let (value, max) = match focus.0 {
+            MenuFocus::MINES => (&mut ui_state.mines, MAX_MINE_COUNT as isize),
+            MenuFocus::ROWS => (&mut ui_state.rows, MAX_BOARD_SIZE as isize),
+            MenuFocus::COLUMNS => (&mut ui_state.columns, MAX_BOARD_SIZE as isize),
+            _ => return,
+        };
+        *value = (*value as isize + delta).clamp(1, max) as usize;
+    }
+
+    if action.just_pressed(GameInteractions::MenuSelect) {
+        match focus.0 {
+            MenuFocus::NEW_GAME => {
+                commands.insert_resource::<FieldGenerationOptions>(ui_state.clone().into());
+                commands.insert_resource(NextState(GameState::Playing))
+            }
+            MenuFocus::PUZZLES => *screen = MenuScreen::Puzzles,
+            MenuFocus::SETTINGS => *screen = MenuScreen::Settings,
+            MenuFocus::CONTROLS => *screen = MenuScreen::Controls,
+            _ => {}
+        }
+    }
+}
+
+/// Lets a controller drive the egui pages, the other menu pages and the pause screen: the menu
+/// actions bound to gamepad buttons reach egui as the keys that move its keyboard focus, change the
+/// focused slider and click the focused widget. The main page has its own [`MenuFocus`].
+pub(crate) fn forward_gamepad_to_egui(state: Res<CurrentState<GameState>>,
+                                      screen: Res<MenuScreen>,
+                                      rebinding: Res<Rebinding>,
+                                      controls: Res<ControlsConfig>,
+                                      buttons: Res<Input<GamepadButton>>,
+                                      mut egui_input: ResMut<HashMap<WindowId, EguiInput>>) {
+    let on_egui_page = match state.0 {
+        GameState::Menu => *screen != MenuScreen::Main,
+        GameState::Paused => true,
+        _ => false,
+    };
+    // a button pressed while rebinding is the new binding
+    if !on_egui_page || rebinding.is_capturing() {
+        return;
+    }
+    let input = match egui_input.get_mut(&WindowId::primary()) {
+        Some(input) => input,
+        None => return,
+    };
+    for (action, bindings) in &controls.game {
+        let pressed = buttons.get_just_pressed()
+            .any(|button| bindings.contains(&Binding::Gamepad(button.1)));
+        if !pressed {
+            continue;
+        }
+        let (key, shift) = match action {
+            GameInteractions::MenuUp => (egui::Key::Tab, true),
+            GameInteractions::MenuDown => (egui::Key::Tab, false),
+            GameInteractions::MenuLeft => (egui::Key::ArrowLeft, false),
+            GameInteractions::MenuRight => (egui::Key::ArrowRight, false),
+            GameInteractions::MenuSelect => (egui::Key::Enter, false),
+            _ => continue,
+        };
+        input.raw_input.events.push(egui::Event::Key {
+            key,
+            pressed: true,
+            modifiers: egui::Modifiers { shift, ..default() },
+        });
+    }
+}
+
+/// Leaves the finished board on screen with a small banner until the player continues to the menu.
+pub(crate) fn show_game_over(txt: Res<TitleText>, options: Res<FieldGenerationOptions>, mut egui_ctx: ResMut<EguiContext>) {
+    let color = Rgba::from_rgba_unmultiplied(txt.1.r(), txt.1.g(), txt.1.b(), txt.1.a());
+    egui::Area::new("GameOver")
+        .anchor(Align2::CENTER_TOP, egui::vec2(0.0, 10.0))
+        .show(egui_ctx.ctx_mut(), |ui| {
+            Frame::none()
+                .inner_margin(Margin::same(5.0))
+                .fill(egui::Color32::from_rgba_unmultiplied(0, 125, 125, 120))
+                .rounding(0.15)
+                .show(ui, |ui| {
+                    ui.vertical_centered(|ui| {
+                        ui.heading(RichText::new(txt.0.clone())
+                            .color(Color32::from(color))
+                            .size(50.0));
+                        if options.has_game_code() {
+                            ui.label(RichText::new(format!("game code {}", GameCode::from(&*options))).size(20.0));
+                        }
+                        ui.label(RichText::new("click, tap or press enter to continue").size(20.0));
+                    });
+                });
+        });
+}
+
+/// Number of mines left to flag and the time played, in the top left corner while a board is shown.
+pub(crate) fn show_hud(field: Res<Field>, timer: Res<GameTimer>, mut egui_ctx: ResMut<EguiContext>) {
+    egui::Area::new("Hud")
+        .anchor(Align2::LEFT_TOP, egui::vec2(10.0, 10.0))
+        .show(egui_ctx.ctx_mut(), |ui| {
+            Frame::none()
+                .inner_margin(Margin::same(5.0))
+                .fill(egui::Color32::from_rgba_unmultiplied(0, 125, 125, 120))
+                .rounding(0.15)
+                .show(ui, |ui| {
+                    ui.label(RichText::new(format!("mines {}   time {}", field.remaining_mines(), timer.text())).size(30.0));
+                });
+        });
+}
+
+pub(crate) fn dismiss_game_over(mut commands: Commands,
+                                game_actions: Query<&ActionState<GameInteractions>>,
+                                field_actions: Query<&ActionState<FieldInteraction>>,
+                                touches: Res<Touches>) {
+    let game = game_actions.single();
+    let field = field_actions.single();
+    if game.just_pressed(GameInteractions::MenuSelect)
+        || game.just_pressed(GameInteractions::OpenMenu)
+        || field.just_pressed(FieldInteraction::Reveal)
+        || touches.iter_just_released().next().is_some() {
+        commands.insert_resource(NextState(GameState::Menu));
+    }
+}
+
+fn focus_text(text: &str, focused: bool) -> RichText {
+    if focused {
+        RichText::new(format!("> {}", text)).color(Color32::YELLOW)
+    } else {
+        RichText::new(text)
+    }
+}
+
+pub(crate) fn theme_combo(ui: &mut egui::Ui, themes: &mut Themes, theme_assets: &Assets<Theme>) {
+    let mut selected = themes.selected;
+    ComboBox::from_id_source("theme")
+        .selected_text(themes.name(selected, theme_assets))
+        .show_ui(ui, |ui| {
+            for i in 0..themes.handles.len() {
+                ui.selectable_value(&mut selected, i, themes.name(i, theme_assets));
+            }
+        });
+    if selected != themes.selected {
+        themes.selected = selected;
+    }
+}
+
+pub(crate) fn skin_combo(ui: &mut egui::Ui, skins: &mut Skins) {
+    let mut selected = skins.selected;
+    ComboBox::from_id_source("skin")
+        .selected_text(skins.name(selected))
+        .show_ui(ui, |ui| {
+            ui.selectable_value(&mut selected, None, skins.name(None));
+            for i in 0..skins.images.len() {
+                ui.selectable_value(&mut selected, Some(i), skins.name(Some(i)));
+            }
+        });
+    if selected != skins.selected {
+        skins.selected = selected;
+    }
+}
+
+pub(crate) fn show_overlay(mut commands: Commands, txt: Res<TitleText>, mut egui_ctx: ResMut<EguiContext>, mut ui_state: ResMut<UiState>, focus: Res<MenuFocus>, mut screen: ResMut<MenuScreen>, stats: Res<GameStats>,
+                           mut themes: ResMut<Themes>, theme_assets: Res<Assets<Theme>>, mut skins: ResMut<Skins>,
+                           generators: Res<GeneratorRegistry>) {
+    if *screen != MenuScreen::Main {
+        return;
+    }
     let ctx = egui_ctx.ctx_mut();
     egui::Area::new("MineSweeper")
         .anchor(Align2::CENTER_CENTER, egui::vec2(0.0, -150.0))
@@ -73,34 +342,131 @@ pub(crate) fn show_overlay(mut commands: Commands, txt: Res<TitleText>, mut egui
                         ui.heading(RichText::new(txt.0.clone())
                             .color(Color32::from(color))
                             .size(75.0));
+                        ui.label(RichText::new(format!("won {} / lost {}", stats.won, stats.lost)).size(25.0));
 
 
                         Grid::new("gird")
                             .min_row_height(10.0)
                             .show(ui, |ui| {
-                                ui.label(RichText::new("mines").size(25.0));
-                                ui.add(Slider::new(&mut ui_state.mines, 1..=100));
+                                ui.label(focus_text("mines", focus.0 == MenuFocus::MINES).size(25.0));
+                                // a density replaces the count
+                                ui.add_enabled(ui_state.constraints.density.is_none(), Slider::new(&mut ui_state.mines, 1..=MAX_MINE_COUNT));
                                 ui.end_row();
 
-                                ui.label(RichText::new("rows").size(25.0));
-                                ui.add(Slider::new(&mut ui_state.rows, 1..=30));
+                                ui.label(focus_text("rows", focus.0 == MenuFocus::ROWS).size(25.0));
+                                ui.add(Slider::new(&mut ui_state.rows, 1..=MAX_BOARD_SIZE));
+                                ui.end_row();
+
+                                ui.label(focus_text("columns", focus.0 == MenuFocus::COLUMNS).size(25.0));
+                                ui.add(Slider::new(&mut ui_state.columns, 1..=MAX_BOARD_SIZE));
+                                ui.end_row();
+                                ui.label(focus_text("theme", focus.0 == MenuFocus::THEME).size(25.0));
+                                theme_combo(ui, &mut themes, &theme_assets);
                                 ui.end_row();
 
-                                ui.label(RichText::new("columns").size(25.0));
-                                ui.add(Slider::new(&mut ui_state.columns, 1..=30));
+                                ui.label(focus_text("skin", focus.0 == MenuFocus::SKIN).size(25.0));
+                                skin_combo(ui, &mut skins);
                                 ui.end_row();
+
+                                ui.label(focus_text("generator", focus.0 == MenuFocus::GENERATOR).size(25.0));
+                                ComboBox::from_id_source("generator")
+                                    .selected_text(ui_state.generator.clone())
+                                    .show_ui(ui, |ui| {
+                                        for name in generators.names() {
+                                            ui.selectable_value(&mut ui_state.generator, name.to_owned(), name);
+                                        }
+                                    });
+                                ui.end_row();
+
                                 ui.label(RichText::new("seed").size(25.0));
                                 ui.text_edit_singleline(&mut ui_state.seed);
                                 ui.end_row();
 
+                                ui.label(RichText::new("code").size(25.0));
+                                let mut code = std::mem::take(&mut ui_state.code);
+                                let response = ui.add(TextEdit::singleline(&mut code).hint_text("none with constraints"))
+                                    .on_hover_text("paste a game code to play the same board");
+                                if response.changed() {
+                                    // codes of generators this build doesn't have can't be played
+                                    let parsed = code.parse::<GameCode>().and_then(|c| match generators.contains(&c.generator) {
+                                        true => Ok(c),
+                                        false => Err(format!("unknown generator {:?}", c.generator)),
+                                    });
+                                    match parsed {
+                                        Ok(parsed) => {
+                                            ui_state.apply_code(parsed);
+                                            ui_state.code_error = None;
+                                        }
+                                        Err(e) => ui_state.code_error = Some(e),
+                                    }
+                                }
+                                ui_state.code = if response.has_focus() {
+                                    code
+                                } else if ui_state.constraints.is_empty() {
+                                    ui_state.code_error = None;
+                                    ui_state.game_code().to_string()
+                                } else {
+                                    ui_state.code_error = None;
+                                    String::new()
+                                };
+                                ui.end_row();
+
+                                if let Some(e) = &ui_state.code_error {
+                                    ui.label("");
+                                    ui.label(RichText::new(e).color(Color32::RED).size(20.0));
+                                    ui.end_row();
+                                }
+
+                                ui.label(RichText::new("density").size(25.0));
+                                ui.horizontal(|ui| {
+                                    let mut enabled = ui_state.constraints.density.is_some();
+                                    let mut density = ui_state.constraints.density.unwrap_or(15.0);
+                                    ui.checkbox(&mut enabled, "")
+                                        .on_hover_text("place a share of the cells as mines instead of a count");
+                                    ui.add_enabled(enabled, Slider::new(&mut density, 1.0..=90.0).suffix("%"));
+                                    ui_state.constraints.density = enabled.then(|| density);
+                                });
+                                ui.end_row();
+
+                                ui.label(RichText::new("max number").size(25.0));
+                                ui.horizontal(|ui| {
+                                    let mut enabled = ui_state.constraints.max_number.is_some();
+                                    let mut max = ui_state.constraints.max_number.unwrap_or(4);
+                                    ui.checkbox(&mut enabled, "");
+                                    ui.add_enabled(enabled, Slider::new(&mut max, 1..=7));
+                                    ui_state.constraints.max_number = enabled.then(|| max);
+                                });
+                                ui.end_row();
+
+                                ui.label(RichText::new("no lone mines").size(25.0));
+                                ui.checkbox(&mut ui_state.constraints.no_isolated_mines, "")
+                                    .on_hover_text("every mine touches another mine");
+                                ui.end_row();
+
                                 ui.end_row();
 
                                 Grid::new("grid2")
                                     .show(ui, |_| {});
-                                if ui.button(RichText::new("New Game").size(50.0)).clicked() {
+                                if ui.button(focus_text("New Game", focus.0 == MenuFocus::NEW_GAME).size(50.0)).clicked() {
                                     commands.insert_resource::<FieldGenerationOptions>(ui_state.clone().into());
                                     commands.insert_resource(NextState(GameState::Playing))
                                 }
+                                ui.end_row();
+
+                                ui.horizontal(|ui| {
+                                    if ui.button(focus_text("Puzzles", focus.0 == MenuFocus::PUZZLES).size(30.0)).clicked() {
+                                        *screen = MenuScreen::Puzzles;
+                                    }
+                                    if ui.button(RichText::new("Editor").size(30.0)).clicked() {
+                                        commands.insert_resource(NextState(GameState::Editor));
+                                    }
+                                    if ui.button(focus_text("Settings", focus.0 == MenuFocus::SETTINGS).size(30.0)).clicked() {
+                                        *screen = MenuScreen::Settings;
+                                    }
+                                    if ui.button(focus_text("Controls", focus.0 == MenuFocus::CONTROLS).size(30.0)).clicked() {
+                                        *screen = MenuScreen::Controls;
+                                    }
+                                });
                             });
                     });
                 });
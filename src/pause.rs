@@ -0,0 +1,84 @@
+use bevy::prelude::*;
+use bevy::window::WindowFocused;
+use bevy_egui::{egui, EguiContext};
+use bevy_egui::egui::{Align, Color32, Frame, Layout, RichText};
+use iyes_loopless::state::NextState;
+use leafwing_input_manager::prelude::ActionState;
+use crate::{Field, FieldGenerationOptions, GameState};
+use crate::field::GameStatus;
+use crate::interactions::GameInteractions;
+use crate::menu::TitleText;
+
+/// Time spent on the current board. It starts with the first reveal and only runs while the game
+/// is played, so pausing freezes it.
+#[derive(Debug, Default)]
+pub struct GameTimer {
+    pub elapsed: f32,
+}
+
+impl GameTimer {
+    pub fn text(&self) -> String {
+        let seconds = self.elapsed as u32;
+        format!("{:02}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+pub(crate) fn reset_timer(mut timer: ResMut<GameTimer>, field_options: Res<FieldGenerationOptions>) {
+    // entering the game from the pause screen keeps the board and its time
+    if field_options.is_changed() {
+        timer.elapsed = 0.0;
+    }
+}
+
+pub(crate) fn tick_timer(mut timer: ResMut<GameTimer>, field: Res<Field>, time: Res<Time>) {
+    if field.status() == GameStatus::InProgress {
+        timer.elapsed += time.delta_seconds();
+    }
+}
+
+pub(crate) fn pause_on_focus_loss(mut commands: Commands, mut events: EventReader<WindowFocused>) {
+    if events.iter().any(|event| !event.focused) {
+        commands.insert_resource(NextState(GameState::Paused));
+    }
+}
+
+pub(crate) fn resume_game(mut commands: Commands, query: Query<&ActionState<GameInteractions>>) {
+    let action = query.single();
+    if action.just_pressed(GameInteractions::OpenMenu) {
+        commands.insert_resource(NextState(GameState::Playing));
+    }
+}
+
+/// Covers the whole board, a paused game must not help with the next move.
+pub(crate) fn show_pause(mut commands: Commands,
+                         mut egui_ctx: ResMut<EguiContext>,
+                         field_options: Res<FieldGenerationOptions>,
+                         mut field: ResMut<Field>,
+                         clear_color: Res<ClearColor>) {
+    let background = clear_color.0;
+    let fill = Color32::from_rgb((background.r() * 255.0) as u8, (background.g() * 255.0) as u8, (background.b() * 255.0) as u8);
+    egui::CentralPanel::default()
+        .frame(Frame::none().fill(fill))
+        .show(egui_ctx.ctx_mut(), |ui| {
+            ui.with_layout(Layout::top_down(Align::Center), |ui| {
+                ui.add_space(ui.available_height() / 4.0);
+                ui.heading(RichText::new("Paused").color(Color32::BLUE).size(75.0));
+                if ui.button(RichText::new("Resume").size(50.0)).clicked() {
+                    commands.insert_resource(NextState(GameState::Playing));
+                }
+                if ui.button(RichText::new("Restart").size(40.0)).clicked() {
+                    commands.insert_resource(field_options.clone());
+                    commands.insert_resource(NextState(GameState::Playing));
+                }
+                if ui.button(RichText::new("Give up").size(40.0)).clicked() {
+                    // the game ends as soon as the revealed field is back in play
+                    field.reveal_all();
+                    commands.insert_resource(NextState(GameState::Playing));
+                }
+                if ui.button(RichText::new("Quit to menu").size(40.0)).clicked() {
+                    commands.insert_resource(TitleText("Mine Sweeper".to_owned(), Color::BLUE));
+                    commands.insert_resource(NextState(GameState::Menu));
+                }
+            });
+        });
+}
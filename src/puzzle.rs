@@ -0,0 +1,353 @@
+use std::collections::HashMap;
+use std::io;
+use bevy::asset::{AssetLoader, AssetPath, BoxedFuture, LoadContext, LoadedAsset};
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use bevy_egui::{egui, EguiContext};
+use bevy_egui::egui::{Align2, Frame, RichText};
+use bevy_egui::egui::style::Margin;
+use iyes_loopless::state::NextState;
+use serde::{Deserialize, Serialize};
+use crate::config::{load_or_default, save_config};
+use crate::field::{Cell, Field};
+use crate::generate::{BoardSource, FieldGenerationOptions, PuzzleRef};
+use crate::interactions::GameOutcome;
+use crate::menu::MenuScreen;
+use crate::GameState;
+
+/// Level packs shipped in `assets/levels`, in the order they are listed in the menu.
+pub(crate) const LEVEL_PACKS: [&str; 1] = [
+    "levels/first_steps.pack.ron",
+];
+const PROGRESS_CONFIG: &str = "progress";
+
+/// Hand-made board from a `*.board.ron` file. `rows` draws the board from top to bottom with one
+/// character per cell: `.` for a hidden cell, `*` for a hidden mine and `o` for a cell that is
+/// already open when the game starts.
+#[derive(Debug, Clone, Deserialize, Serialize, TypeUuid)]
+#[uuid = "2c9a374c-7a73-4a0b-af93-79a3f2f56e2b"]
+pub struct PuzzleBoard {
+    /// Shown while the board is played.
+    #[serde(default)]
+    pub message: Option<String>,
+    pub rows: Vec<String>,
+}
+
+impl PuzzleBoard {
+    /// Board of `width` by `height` hidden cells without mines.
+    pub fn empty(width: usize, height: usize) -> Self {
+        Self {
+            message: None,
+            rows: vec![".".repeat(width); height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.rows.first().map_or(0, |row| row.chars().count())
+    }
+
+    pub fn height(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Character of the cell at `x` and `y`, counted from the bottom left like cell handles.
+    pub(crate) fn tile(&self, x: usize, y: usize) -> char {
+        self.rows[self.height() - 1 - y].chars().nth(x).unwrap_or('.')
+    }
+
+    pub(crate) fn set_tile(&mut self, x: usize, y: usize, tile: char) {
+        let row = self.height() - 1 - y;
+        self.rows[row] = self.rows[row].chars()
+            .enumerate()
+            .map(|(i, c)| if i == x { tile } else { c })
+            .collect();
+    }
+
+    /// Changes the size of the board, the top left corner stays in place and new cells are hidden.
+    pub fn resize(&mut self, width: usize, height: usize) {
+        self.rows.resize(height, String::new());
+        for row in &mut self.rows {
+            *row = row.chars().chain(std::iter::repeat('.')).take(width).collect();
+        }
+    }
+
+    pub fn mine_count(&self) -> usize {
+        self.rows.iter().map(|row| row.chars().filter(|&c| c == '*').count()).sum()
+    }
+
+    /// Checks that the rows form a rectangle of known characters with at least one hidden safe
+    /// cell, a board without one could never be won.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.width() == 0 {
+            return Err("the board has no cells".to_owned());
+        }
+        for (i, row) in self.rows.iter().enumerate() {
+            if row.chars().count() != self.width() {
+                return Err(format!("row {} has {} cells instead of {}", i + 1, row.chars().count(), self.width()));
+            }
+            if let Some(c) = row.chars().find(|c| !matches!(c, '.' | '*' | 'o')) {
+                return Err(format!("row {} contains {:?}, expected '.', '*' or 'o'", i + 1, c));
+            }
+        }
+        if !self.rows.iter().any(|row| row.contains('.')) {
+            return Err("the board has no hidden safe cell to reveal".to_owned());
+        }
+        Ok(())
+    }
+
+    /// Options that start this board as level `puzzle`.
+    pub fn options(&self, puzzle: PuzzleRef) -> FieldGenerationOptions {
+        FieldGenerationOptions {
+            width: self.width(),
+            height: self.height(),
+            mine_count: self.mine_count(),
+            seed: 0,
+            puzzle: Some(puzzle),
+            ..default()
+        }
+    }
+}
+
+/// Builds the field of a hand-made board, usable wherever a generated board is.
+pub struct PuzzleGenerator<'a> {
+    board: &'a PuzzleBoard,
+}
+
+impl<'a> PuzzleGenerator<'a> {
+    pub fn new(board: &'a PuzzleBoard) -> Self {
+        Self { board }
+    }
+}
+
+impl BoardSource for PuzzleGenerator<'_> {
+    fn generate(&mut self, _options: &FieldGenerationOptions) -> Field {
+        let (width, height) = (self.board.width(), self.board.height());
+        let mut elements = Vec::with_capacity(width * height);
+        for x in 0..width {
+            for y in 0..height {
+                let mut cell = Cell::new();
+                cell.is_mine = self.board.tile(x, y) == '*';
+                elements.push(cell);
+            }
+        }
+        let mut field = Field::new(width, height, elements, self.board.mine_count());
+        for cell in field.get_handles() {
+            if self.board.tile(cell.x, cell.y) == 'o' {
+                field.open(cell);
+            }
+        }
+        field
+    }
+}
+
+#[derive(Default)]
+pub struct PuzzleBoardLoader;
+
+impl AssetLoader for PuzzleBoardLoader {
+    fn load<'a>(&'a self, bytes: &'a [u8], load_context: &'a mut LoadContext) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let board: PuzzleBoard = ron::de::from_bytes(bytes)?;
+            board.validate().map_err(|e| anyhow::anyhow!("{}: {}", load_context.path().display(), e))?;
+            load_context.set_default_asset(LoadedAsset::new(board));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["board.ron"]
+    }
+}
+
+/// Layout of a `*.pack.ron` file, the levels are board files relative to the pack.
+#[derive(Debug, Deserialize)]
+struct LevelPackFile {
+    name: String,
+    levels: Vec<String>,
+}
+
+/// Boards that are played one after another, each one unlocks the next.
+#[derive(Debug, TypeUuid)]
+#[uuid = "36fe552b-ab14-4fa3-b0b6-71a3f8ddce16"]
+pub struct LevelPack {
+    pub name: String,
+    pub levels: Vec<Handle<PuzzleBoard>>,
+}
+
+#[derive(Default)]
+pub struct LevelPackLoader;
+
+impl AssetLoader for LevelPackLoader {
+    fn load<'a>(&'a self, bytes: &'a [u8], load_context: &'a mut LoadContext) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let file: LevelPackFile = ron::de::from_bytes(bytes)?;
+            let folder = load_context.path().parent().map(|p| p.to_path_buf()).unwrap_or_default();
+            let paths = file.levels.iter()
+                .map(|level| AssetPath::new(folder.join(level), None))
+                .collect::<Vec<_>>();
+            let pack = LevelPack {
+                name: file.name,
+                levels: paths.iter().map(|path| load_context.get_handle(path.clone())).collect(),
+            };
+            load_context.set_default_asset(LoadedAsset::new(pack).with_dependencies(paths));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["pack.ron"]
+    }
+}
+
+/// All level packs, in the order of [`LEVEL_PACKS`].
+pub struct LevelPacks {
+    pub handles: Vec<Handle<LevelPack>>,
+}
+
+impl LevelPacks {
+    pub fn load(asset_server: &AssetServer) -> Self {
+        Self {
+            handles: LEVEL_PACKS.iter().map(|path| asset_server.load(*path)).collect(),
+        }
+    }
+
+    /// The board of `puzzle` if its pack and board have finished loading.
+    pub fn board<'a>(&self, puzzle: PuzzleRef, packs: &Assets<LevelPack>, boards: &'a Assets<PuzzleBoard>) -> Option<&'a PuzzleBoard> {
+        let pack = packs.get(self.handles.get(puzzle.pack)?)?;
+        boards.get(pack.levels.get(puzzle.level)?)
+    }
+}
+
+/// Number of solved levels per pack name, kept in `config/progress.ron`. Levels are unlocked in
+/// order, so the count is enough.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LevelProgress {
+    pub solved: HashMap<String, usize>,
+}
+
+impl LevelProgress {
+    pub fn load_or_default() -> Self {
+        let (progress, error) = load_or_default(PROGRESS_CONFIG);
+        if let Some(e) = error {
+            warn!("Could not load level progress, starting over: {}", e);
+        }
+        progress
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        save_config(PROGRESS_CONFIG, self)
+    }
+
+    pub fn is_unlocked(&self, pack: &str, level: usize) -> bool {
+        level <= self.solved.get(pack).copied().unwrap_or(0)
+    }
+}
+
+/// Unlocks the next level when a level is won.
+pub(crate) fn record_puzzle_progress(mut events: EventReader<GameOutcome>,
+                                     options: Res<FieldGenerationOptions>,
+                                     packs: Res<LevelPacks>,
+                                     pack_assets: Res<Assets<LevelPack>>,
+                                     mut progress: ResMut<LevelProgress>) {
+    if !events.iter().any(|outcome| *outcome == GameOutcome::Won) {
+        return;
+    }
+    let puzzle = match options.puzzle {
+        Some(puzzle) => puzzle,
+        None => return,
+    };
+    let pack = match packs.handles.get(puzzle.pack).and_then(|handle| pack_assets.get(handle)) {
+        Some(pack) => pack,
+        None => return,
+    };
+    let solved = progress.solved.entry(pack.name.clone()).or_default();
+    if puzzle.level + 1 > *solved {
+        *solved = puzzle.level + 1;
+        if let Err(e) = progress.save() {
+            warn!("Could not save level progress: {}", e);
+        }
+    }
+}
+
+/// Message of the hand-made board that is being played, at the bottom of the screen.
+pub(crate) fn show_puzzle_message(options: Res<FieldGenerationOptions>,
+                                  packs: Res<LevelPacks>,
+                                  pack_assets: Res<Assets<LevelPack>>,
+                                  boards: Res<Assets<PuzzleBoard>>,
+                                  mut egui_ctx: ResMut<EguiContext>) {
+    let message = options.puzzle
+        .and_then(|puzzle| packs.board(puzzle, &pack_assets, &boards))
+        .and_then(|board| board.message.as_ref());
+    let message = match message {
+        Some(message) => message,
+        None => return,
+    };
+    egui::Area::new("PuzzleMessage")
+        .anchor(Align2::CENTER_BOTTOM, egui::vec2(0.0, -10.0))
+        .show(egui_ctx.ctx_mut(), |ui| {
+            Frame::none()
+                .inner_margin(Margin::same(5.0))
+                .fill(egui::Color32::from_rgba_unmultiplied(0, 125, 125, 120))
+                .rounding(0.15)
+                .show(ui, |ui| {
+                    ui.label(RichText::new(message).size(25.0));
+                });
+        });
+}
+
+/// Menu page with every level pack, locked levels are shown but can't be started.
+pub(crate) fn show_puzzles(mut commands: Commands,
+                           mut egui_ctx: ResMut<EguiContext>,
+                           mut screen: ResMut<MenuScreen>,
+                           packs: Res<LevelPacks>,
+                           pack_assets: Res<Assets<LevelPack>>,
+                           boards: Res<Assets<PuzzleBoard>>,
+                           progress: Res<LevelProgress>) {
+    if *screen != MenuScreen::Puzzles {
+        return;
+    }
+    let ctx = egui_ctx.ctx_mut();
+    egui::Area::new("Puzzles")
+        .anchor(Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+        .show(ctx, |ui| {
+            Frame::none()
+                .inner_margin(Margin::same(5.0))
+                .fill(egui::Color32::from_rgba_unmultiplied(0, 125, 125, 220))
+                .rounding(0.15)
+                .show(ui, |ui| {
+                    ui.vertical(|ui| {
+                        ui.heading(RichText::new("Puzzles").size(50.0));
+
+                        for (pack_index, handle) in packs.handles.iter().enumerate() {
+                            let pack = match pack_assets.get(handle) {
+                                Some(pack) => pack,
+                                None => {
+                                    ui.label(RichText::new("loading...").size(25.0));
+                                    continue;
+                                }
+                            };
+                            let solved = progress.solved.get(&pack.name).copied().unwrap_or(0);
+                            ui.label(RichText::new(format!("{}   {} / {}", pack.name, solved.min(pack.levels.len()), pack.levels.len())).size(30.0));
+                            ui.horizontal_wrapped(|ui| {
+                                for level in 0..pack.levels.len() {
+                                    let puzzle = PuzzleRef { pack: pack_index, level };
+                                    let board = packs.board(puzzle, &pack_assets, &boards);
+                                    let unlocked = progress.is_unlocked(&pack.name, level) && board.is_some();
+                                    let text = RichText::new(format!("{}", level + 1)).size(30.0);
+                                    if ui.add_enabled(unlocked, egui::Button::new(text)).clicked() {
+                                        if let Some(board) = board {
+                                            commands.insert_resource(board.options(puzzle));
+                                            commands.insert_resource(NextState(GameState::Playing));
+                                        }
+                                    }
+                                }
+                            });
+                        }
+
+                        if ui.button(RichText::new("Back").size(30.0)).clicked() {
+                            *screen = MenuScreen::Main;
+                        }
+                    });
+                });
+        });
+}
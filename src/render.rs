@@ -1,59 +1,128 @@
 use bevy::prelude::*;
-use crate::{CellComponent, CellState, ColorMaterial, Field, Mark, StateMaterials, Text2dBounds, TextFont};
+use crate::animation::{animation_for, CellAnimation, RippleOrigin, ShownState};
+use crate::settings::Settings;
+use crate::skin::Tile;
+use crate::{CellComponent, CellEntities, CellHandle, CellState, ColorMaterial, Field, Mark, StateMaterials, Text2dBounds, TextFont};
 
-pub(crate) fn update_render(mut query: Query<(Entity, &CellComponent, &Handle<ColorMaterial>, Option<&Children>)>,
+/// What a cell should look like. The render system either applies it right away or hands it to
+/// the animation that plays for the change.
+#[derive(Debug, Clone)]
+pub(crate) enum Look {
+    Flat { color: Color, label: Option<(String, Color)> },
+    Sprite(usize),
+}
+
+pub(crate) fn update_render(mut query: Query<(&CellComponent, &mut Transform, Option<&Handle<ColorMaterial>>, Option<&mut TextureAtlasSprite>, Option<&Children>, Option<&ShownState>)>,
                  mut materials: ResMut<Assets<ColorMaterial>>,
                  colors: Res<StateMaterials>,
                  mut field: ResMut<Field>,
+                 entities: Res<CellEntities>,
                  mut commands: Commands,
+                 settings: Res<Settings>,
+                 origin: Res<RippleOrigin>,
                  font: Res<TextFont>) {
-    for (e, cell, m, childs) in query.iter_mut() {
-        if field[cell.handle].is_dirty {
-            let color = match field[cell.handle].get_state() {
-                CellState::Hidden => {
-                    colors.hidden.clone()
-                },
-                CellState::Revealed => {
-                    if field[cell.handle].is_mine {
-                        colors.mine.clone()
-                    } else {
-                        if childs.is_none() {
-                            let adj = field.get_adjacent_mines(cell.handle);
-                            if adj > 0 {
-                                let text = commands.spawn_bundle(Text2dBundle {
-                                    text: Text::with_section(adj.to_string(),
-                                                             TextStyle {
-                                                                 font: font.0.clone(),
-                                                                 color: colors.colors_numbers[&(adj as u8)],
-                                                                 font_size: 50.0,
-                                                             }, TextAlignment {
-                                            vertical: VerticalAlign::Center,
-                                            horizontal: HorizontalAlign::Center,
-                                        }),
-                                    text_2d_bounds: Text2dBounds {
-                                        size: Size::new(100.0, 100.0)
-                                    },
-                                    transform: Transform::default().with_translation(Vec3::new(0.0, 0.0, 2.0)),
-                                    ..default()
-                                }).id();
-                                commands.entity(e).add_child(text);
-                            } else {
-                                let empty = commands.spawn().id();
-                                commands.entity(e).add_child(empty);
-                            }
-                        }
-                        colors.revealed.clone()
-                    }
-                },
-                CellState::Marked(m) => {
-                    match m {
-                        Mark::Mine => colors.marked_mine.clone(),
-                        Mark::Empty => colors.marked_empty.clone(),
-                    }
-                }
+    // taking the changes below doesn't count as a change for this system itself
+    if !field.is_changed() {
+        return;
+    }
+    for handle in field.take_changes() {
+        let e = match entities.get(&handle) {
+            Some(&e) => e,
+            None => continue,
+        };
+        if let Ok((cell, mut transform, m, sprite, childs, shown)) = query.get_mut(e) {
+            let look = if sprite.is_some() {
+                Look::Sprite(Tile::for_cell(&mut field, cell.handle).index())
+            } else {
+                flat_look(&mut field, cell.handle, &colors)
             };
-            materials.get_mut(m).expect("Material not found").color = color;
-            field[cell.handle].is_dirty = false;
+            let state = field[cell.handle].get_state();
+            match animation_for(&field, cell.handle, shown, &settings, &origin) {
+                Some(animation) => {
+                    commands.entity(e).insert(animation.with_look(look));
+                }
+                None => {
+                    // a newer state wins over an animation that is still playing
+                    commands.entity(e).remove::<CellAnimation>();
+                    transform.scale = Vec3::ONE;
+                    apply_look(&mut commands, e, look, m, sprite, childs, &mut materials, &font);
+                }
+            }
+            commands.entity(e).insert(ShownState(state));
+        }
+    }
+}
+
+fn flat_look(field: &mut Field, handle: CellHandle, colors: &StateMaterials) -> Look {
+    let (color, label) = match field[handle].get_state() {
+        CellState::Hidden => (colors.hidden.clone(), None),
+        CellState::Revealed => {
+            if field[handle].is_mine {
+                if field.detonated() == Some(handle) {
+                    (colors.exploded.clone(), None)
+                } else {
+                    (colors.mine.clone(), None)
+                }
+            } else {
+                let adj = field.get_adjacent_mines(handle);
+                let label = (adj > 0).then(|| (adj.to_string(), colors.colors_numbers[&(adj as u8)]));
+                (colors.revealed.clone(), label)
+            }
+        },
+        CellState::Marked(Mark::Mine) => {
+            // cross out flags that turned out to be wrong once the game is lost
+            let wrong = field.detonated().is_some() && !field[handle].is_mine;
+            (colors.marked_mine.clone(), wrong.then(|| ("X".to_owned(), Color::BLACK)))
+        }
+        CellState::Marked(Mark::Question) => (colors.hidden.clone(), Some(("?".to_owned(), *colors.question))),
+    };
+    Look::Flat { color, label }
+}
+
+/// Puts `look` on the cell entity, replacing the label of a flat cell.
+pub(crate) fn apply_look(commands: &mut Commands,
+                         e: Entity,
+                         look: Look,
+                         material: Option<&Handle<ColorMaterial>>,
+                         sprite: Option<Mut<TextureAtlasSprite>>,
+                         childs: Option<&Children>,
+                         materials: &mut Assets<ColorMaterial>,
+                         font: &TextFont) {
+    match look {
+        Look::Sprite(index) => {
+            if let Some(mut sprite) = sprite {
+                sprite.index = index;
+                sprite.color = Color::WHITE;
+            }
+        }
+        Look::Flat { color, label } => {
+            if let Some(childs) = childs {
+                for child in childs.iter() {
+                    commands.entity(*child).despawn_recursive();
+                }
+            }
+            if let Some((text, color)) = label {
+                let text = commands.spawn_bundle(Text2dBundle {
+                    text: Text::with_section(text,
+                                             TextStyle {
+                                                 font: font.0.clone(),
+                                                 color,
+                                                 font_size: 50.0,
+                                             }, TextAlignment {
+                            vertical: VerticalAlign::Center,
+                            horizontal: HorizontalAlign::Center,
+                        }),
+                    text_2d_bounds: Text2dBounds {
+                        size: Size::new(100.0, 100.0)
+                    },
+                    transform: Transform::default().with_translation(Vec3::new(0.0, 0.0, 2.0)),
+                    ..default()
+                }).id();
+                commands.entity(e).add_child(text);
+            }
+            if let Some(material) = material.and_then(|m| materials.get_mut(m)) {
+                material.color = color;
+            }
         }
     }
-}
\ No newline at end of file
+}
@@ -1,7 +1,8 @@
-use crate::{CellHandle, Field, RevealResult};
+use crate::{CellHandle, CellState, Field, Mark, RevealResult};
 
 pub trait Revealer {
     fn reveal_area(field: &mut Field, handle: CellHandle);
+    fn chord(field: &mut Field, handle: CellHandle) -> RevealResult;
 }
 
 pub struct RevealerImpl;
@@ -21,7 +22,7 @@ impl Revealer for RevealerImpl {
                     field.reveal(neighbor);
                     Self::reveal_area(field, neighbor);
                 }
-                RevealResult::AlreadyRevealed => {
+                RevealResult::AlreadyRevealed | RevealResult::GameEnded => {
                     // do nothing
                 }
                 RevealResult::Empty(_) => {
@@ -30,5 +31,41 @@ impl Revealer for RevealerImpl {
             }
         }
     }
-}
 
+    fn chord(field: &mut Field, handle: CellHandle) -> RevealResult {
+        if field[handle].get_state() != CellState::Revealed || field[handle].is_mine {
+            return RevealResult::AlreadyRevealed;
+        }
+        let adjacent_mines = field.get_adjacent_mines(handle);
+        let neighbors = field.get_neighbors(handle);
+        let flagged = neighbors.iter().filter(|&&n| field[n].get_state() == CellState::Marked(Mark::Mine)).count();
+        if flagged != adjacent_mines {
+            return RevealResult::AlreadyRevealed;
+        }
+
+        let mut result = RevealResult::AlreadyRevealed;
+        for neighbor in neighbors {
+            match field[neighbor].get_state() {
+                CellState::Revealed | CellState::Marked(Mark::Mine) => continue,
+                _ => {}
+            }
+            match field.reveal(neighbor) {
+                RevealResult::Mine => {
+                    result = RevealResult::Mine;
+                }
+                RevealResult::Empty(adjacent) => {
+                    if adjacent == 0 {
+                        Self::reveal_area(field, neighbor);
+                    }
+                    if !matches!(result, RevealResult::Mine) {
+                        result = RevealResult::Empty(adjacent_mines);
+                    }
+                }
+                RevealResult::AlreadyRevealed | RevealResult::GameEnded => {
+                    // do nothing
+                }
+            }
+        }
+        result
+    }
+}
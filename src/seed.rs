@@ -0,0 +1,214 @@
+//! Seeds and game codes.
+//!
+//! A board is fully described by its width, height, mine count and a 64 bit seed. Version 2 of the
+//! mapping from those values to mine positions is:
+//!
+//! 1. The seed text is used as is if it is a decimal `u64`, otherwise it is hashed with 64 bit
+//!    FNV-1a over its UTF-8 bytes.
+//! 2. A SplitMix64 generator is seeded with that value. `below(n)` is the high 64 bits of
+//!    `next_u64() * n`.
+//! 3. The cells are indexed `0..len` with `len = width * height`. With `count` the mine count
+//!    capped at `len`, [`StableMineSelector`](crate::generate::StableMineSelector) runs Floyd's
+//!    algorithm: for every `j` in `len - count..len`, `t = below(j + 1)` becomes a mine, or `j`
+//!    if `t` already is one.
+//!
+//! Version 1 differs in step 3: the indices are partially shuffled with Fisher-Yates, for every
+//! mine `i` the index at `i` is swapped with the one at `i + below(len - i)` and the first `count`
+//! indices are the mines. Its codes are played with the `stable-v1` generator.
+//!
+//! Changing any of these steps changes existing boards and has to come with a new
+//! [`SEED_FORMAT_VERSION`]. Boards of the other generators in the
+//! [`GeneratorRegistry`](crate::generate::GeneratorRegistry) also start from the seed, but depend
+//! on the version of the crate that provides their RNG.
+
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use crate::generate::{FieldGenerationOptions, STABLE_GENERATOR, STABLE_V1_GENERATOR};
+
+/// Version of the seed to board mapping described above, part of every game code.
+pub const SEED_FORMAT_VERSION: u32 = 2;
+/// Largest width and height of a board, the menu offers the same range.
+pub const MAX_BOARD_SIZE: usize = 30;
+/// Largest mine count of a board, the menu offers the same range.
+pub const MAX_MINE_COUNT: usize = 100;
+
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// Turns the seed typed into the menu into the number the board is generated from.
+pub fn seed_from_str(seed: &str) -> u64 {
+    let seed = seed.trim();
+    seed.parse().unwrap_or_else(|_| {
+        seed.bytes().fold(FNV_OFFSET, |hash, byte| (hash ^ byte as u64).wrapping_mul(FNV_PRIME))
+    })
+}
+
+/// SplitMix64, small and fully specified so boards don't depend on the version of an RNG crate.
+#[derive(Debug, Clone)]
+pub struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number in `0..n`.
+    pub fn below(&mut self, n: u64) -> u64 {
+        ((self.next_u64() as u128 * n as u128) >> 64) as u64
+    }
+}
+
+/// Everything needed to play the same board again, written as `<version>-<width>x<height>-<mines>-<seed>`
+/// with the seed in base 36, e.g. `2-20x20-40-2kp4y8f1q9xd`. Boards of another generator than the
+/// stable one add its name as a last part, e.g. `2-20x20-40-2kp4y8f1q9xd-chacha8`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameCode {
+    pub version: u32,
+    pub width: usize,
+    pub height: usize,
+    pub mine_count: usize,
+    pub seed: u64,
+    pub generator: String,
+}
+
+impl From<&FieldGenerationOptions> for GameCode {
+    fn from(options: &FieldGenerationOptions) -> Self {
+        Self {
+            version: SEED_FORMAT_VERSION,
+            width: options.width,
+            height: options.height,
+            mine_count: options.mine_count,
+            seed: options.seed,
+            generator: options.generator.clone(),
+        }
+    }
+}
+
+impl Display for GameCode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}x{}-{}-{}", self.version, self.width, self.height, self.mine_count, to_base36(self.seed))?;
+        if self.generator != STABLE_GENERATOR {
+            write!(f, "-{}", self.generator)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for GameCode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // generator names may contain dashes themselves
+        let parts = s.trim().splitn(5, '-').collect::<Vec<_>>();
+        if parts.len() != 4 && parts.len() != 5 {
+            return Err("expected <version>-<width>x<height>-<mines>-<seed>[-<generator>]".to_owned());
+        }
+        let version = parts[0].parse::<u32>().map_err(|_| format!("invalid version {:?}", parts[0]))?;
+        if version == 0 || version > SEED_FORMAT_VERSION {
+            return Err(format!("version {} is not supported, this game uses version {}", version, SEED_FORMAT_VERSION));
+        }
+        let (width, height) = parts[1].split_once('x').ok_or_else(|| format!("invalid size {:?}", parts[1]))?;
+        let number = |text: &str, what: &str, max: usize| match text.parse::<usize>() {
+            Ok(value) if (1..=max).contains(&value) => Ok(value),
+            Ok(_) => Err(format!("{} {} is not within 1 to {}", what, text, max)),
+            Err(_) => Err(format!("invalid {} {:?}", what, text)),
+        };
+        let width = number(width, "width", MAX_BOARD_SIZE)?;
+        let height = number(height, "height", MAX_BOARD_SIZE)?;
+        let mine_count = number(parts[2], "mine count", MAX_MINE_COUNT)?;
+        if mine_count >= width * height {
+            return Err(format!("{} mines leave no safe cell on a {}x{} board", mine_count, width, height));
+        }
+        Ok(Self {
+            version,
+            width,
+            height,
+            mine_count,
+            seed: from_base36(parts[3]).ok_or_else(|| format!("invalid seed {:?}", parts[3]))?,
+            generator: match parts.get(4) {
+                Some(generator) => generator.to_string(),
+                None if version == 1 => STABLE_V1_GENERATOR.to_owned(),
+                None => STABLE_GENERATOR.to_owned(),
+            },
+        })
+    }
+}
+
+fn to_base36(mut value: u64) -> String {
+    const DIGITS: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+    let mut digits = Vec::new();
+    loop {
+        digits.push(DIGITS[(value % 36) as usize]);
+        value /= 36;
+        if value == 0 {
+            break;
+        }
+    }
+    digits.iter().rev().map(|&d| d as char).collect()
+}
+
+fn from_base36(text: &str) -> Option<u64> {
+    if text.is_empty() {
+        return None;
+    }
+    text.chars().try_fold(0u64, |value, c| {
+        value.checked_mul(36)?.checked_add(c.to_digit(36)? as u64)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seed_text_is_hashed_with_fnv1a() {
+        assert_eq!(seed_from_str(""), 0xcbf29ce484222325);
+        assert_eq!(seed_from_str("a"), 0xaf63dc4c8601ec8c);
+        assert_eq!(seed_from_str(" minesweeper "), 0x16d87bf577f941b5);
+    }
+
+    #[test]
+    fn decimal_seeds_are_used_as_is() {
+        assert_eq!(seed_from_str("12345"), 12345);
+        assert_eq!(seed_from_str("18446744073709551615"), u64::MAX);
+    }
+
+    #[test]
+    fn split_mix_matches_the_reference() {
+        let mut rng = SplitMix64::new(0);
+        assert_eq!(rng.next_u64(), 0xe220a8397b1dcdaf);
+        assert_eq!(rng.next_u64(), 0x6e789e6aa1b965f4);
+        assert_eq!(rng.next_u64(), 0x06c45d188009454f);
+    }
+
+    #[test]
+    fn game_codes_round_trip() {
+        for text in ["2-20x20-40-2kp4y8f1q9xd", "2-8x16-10-0", "2-30x30-100-3w5e11264sgsf-chacha8", "1-9x9-10-z-stable-v1"] {
+            assert_eq!(text.parse::<GameCode>().unwrap().to_string(), text);
+        }
+    }
+
+    #[test]
+    fn version_1_codes_use_the_version_1_generator() {
+        let code = "1-9x9-10-z".parse::<GameCode>().unwrap();
+        assert_eq!(code.generator, STABLE_V1_GENERATOR);
+        assert_eq!(code.seed, 35);
+    }
+
+    #[test]
+    fn codes_outside_the_menu_ranges_are_rejected() {
+        for text in ["2-0x0-5-abc", "2-31x10-5-abc", "2-10x10-0-abc", "2-10x10-101-abc", "2-2x2-4-abc", "3-10x10-5-abc", "2-10x10-5-!"] {
+            assert!(text.parse::<GameCode>().is_err(), "{} was accepted", text);
+        }
+    }
+}
@@ -0,0 +1,222 @@
+use std::io;
+use bevy::prelude::*;
+use bevy::window::WindowMode;
+use bevy_egui::{egui, EguiContext};
+use bevy_egui::egui::{Align2, ComboBox, Frame, Grid, RichText, Slider};
+use bevy_egui::egui::style::Margin;
+use serde::{Deserialize, Serialize};
+use crate::config::{load_or_default, save_config};
+use crate::menu::{MenuScreen, skin_combo, theme_combo};
+use crate::skin::Skins;
+use crate::theme::{Theme, THEME_FILES, Themes};
+use crate::window::{WindowConfig, WindowModeConfig};
+
+const SETTINGS_CONFIG: &str = "settings";
+/// Time without further changes before the settings are written, so dragging a slider doesn't
+/// write the file every frame.
+const SAVE_DELAY: f64 = 0.5;
+/// Window sizes offered in the settings, the size of the current window is added if it is missing.
+const RESOLUTIONS: [(f32, f32); 6] = [
+    (800.0, 800.0),
+    (1024.0, 768.0),
+    (1280.0, 720.0),
+    (1280.0, 1024.0),
+    (1600.0, 900.0),
+    (1920.0, 1080.0),
+];
+
+/// Gameplay options that are kept between sessions in `config/settings.ron`.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Whether marking a flagged cell again turns it into a question mark.
+    pub question_marks: bool,
+    /// Whether reveals, flags and explosions are animated or drawn instantly.
+    pub animations: bool,
+    /// Volume of the sound effects from 0 to 1.
+    pub volume: f32,
+    pub muted: bool,
+    /// Path of the selected theme below `assets`.
+    pub theme: String,
+    /// Name of the selected skin, flat colours if there is none.
+    pub skin: Option<String>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            question_marks: true,
+            animations: true,
+            volume: 0.7,
+            muted: false,
+            theme: THEME_FILES[0].to_owned(),
+            skin: None,
+        }
+    }
+}
+
+impl Settings {
+    pub fn load_or_default() -> Self {
+        let (settings, error) = load_or_default(SETTINGS_CONFIG);
+        if let Some(e) = error {
+            warn!("Could not load settings, using defaults: {}", e);
+        }
+        settings
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        save_config(SETTINGS_CONFIG, self)
+    }
+}
+
+/// Writes the settings once they stopped changing and keeps the selected theme and skin in them.
+pub(crate) fn persist_settings(mut settings: ResMut<Settings>,
+                               themes: Res<Themes>,
+                               skins: Res<Skins>,
+                               time: Res<Time>,
+                               mut pending: Local<Option<f64>>) {
+    if themes.is_changed() && !themes.is_added() {
+        let theme = THEME_FILES[themes.selected].to_owned();
+        if settings.theme != theme {
+            settings.theme = theme;
+        }
+    }
+    if skins.is_changed() && !skins.is_added() {
+        let skin = skins.selected.map(|i| skins.names[i].clone());
+        if settings.skin != skin {
+            settings.skin = skin;
+        }
+    }
+
+    let now = time.seconds_since_startup();
+    if settings.is_changed() && !settings.is_added() {
+        *pending = Some(now);
+    }
+    if let Some(changed) = *pending {
+        if now - changed > SAVE_DELAY {
+            *pending = None;
+            if let Err(e) = settings.save() {
+                warn!("Could not save settings: {}", e);
+            }
+        }
+    }
+}
+
+pub(crate) fn show_settings(mut egui_ctx: ResMut<EguiContext>,
+                            mut screen: ResMut<MenuScreen>,
+                            mut settings: ResMut<Settings>,
+                            mut window: ResMut<WindowConfig>,
+                            mut themes: ResMut<Themes>,
+                            theme_assets: Res<Assets<Theme>>,
+                            mut skins: ResMut<Skins>) {
+    if *screen != MenuScreen::Settings {
+        return;
+    }
+    let ctx = egui_ctx.ctx_mut();
+    egui::Area::new("Settings")
+        .anchor(Align2::CENTER_CENTER, egui::vec2(0.0, 0.0))
+        .show(ctx, |ui| {
+            Frame::none()
+                .inner_margin(Margin::same(5.0))
+                .fill(egui::Color32::from_rgba_unmultiplied(0, 125, 125, 220))
+                .rounding(0.15)
+                .show(ui, |ui| {
+                    ui.vertical(|ui| {
+                        ui.heading(RichText::new("Settings").size(50.0));
+
+                        Grid::new("settings_grid").show(ui, |ui| {
+                            // widgets work on copies so the resources are only touched on actual changes
+                            if !cfg!(target_arch = "wasm32") {
+                                ui.label(RichText::new("window mode").size(25.0));
+                                let mut mode = window.mode.clone();
+                                ComboBox::from_id_source("window_mode")
+                                    .selected_text(mode.name().to_owned())
+                                    .show_ui(ui, |ui| {
+                                        for option in WindowModeConfig::ALL {
+                                            let option = WindowModeConfig::from(option);
+                                            let name = option.name().to_owned();
+                                            ui.selectable_value(&mut mode, option, name);
+                                        }
+                                    });
+                                if mode != window.mode {
+                                    window.mode = mode;
+                                }
+                                ui.end_row();
+
+                                ui.label(RichText::new("resolution").size(25.0));
+                                let mut resolution = (window.width, window.height);
+                                let mut options = RESOLUTIONS.to_vec();
+                                if !options.contains(&resolution) {
+                                    options.insert(0, resolution);
+                                }
+                                ui.add_enabled_ui(window.mode == WindowMode::Windowed.into(), |ui| {
+                                    ComboBox::from_id_source("resolution")
+                                        .selected_text(format!("{} x {}", resolution.0, resolution.1))
+                                        .show_ui(ui, |ui| {
+                                            for option in options {
+                                                ui.selectable_value(&mut resolution, option, format!("{} x {}", option.0, option.1));
+                                            }
+                                        });
+                                });
+                                if resolution != (window.width, window.height) {
+                                    window.width = resolution.0;
+                                    window.height = resolution.1;
+                                }
+                                ui.end_row();
+
+                                ui.label(RichText::new("vsync").size(25.0));
+                                let mut vsync = window.vsync;
+                                if ui.checkbox(&mut vsync, "").changed() {
+                                    window.vsync = vsync;
+                                }
+                                ui.end_row();
+                            }
+
+                            ui.label(RichText::new("theme").size(25.0));
+                            theme_combo(ui, &mut themes, &theme_assets);
+                            ui.end_row();
+
+                            ui.label(RichText::new("skin").size(25.0));
+                            skin_combo(ui, &mut skins);
+                            ui.end_row();
+
+                            ui.label(RichText::new("animations").size(25.0));
+                            let mut animations = settings.animations;
+                            if ui.checkbox(&mut animations, "").changed() {
+                                settings.animations = animations;
+                            }
+                            ui.end_row();
+
+                            ui.label(RichText::new("question marks").size(25.0));
+                            let mut question_marks = settings.question_marks;
+                            if ui.checkbox(&mut question_marks, "").changed() {
+                                settings.question_marks = question_marks;
+                            }
+                            ui.end_row();
+
+                            ui.label(RichText::new("volume").size(25.0));
+                            ui.horizontal(|ui| {
+                                let mut volume = settings.volume;
+                                if ui.add(Slider::new(&mut volume, 0.0..=1.0)).changed() {
+                                    settings.volume = volume;
+                                }
+                                let mut muted = settings.muted;
+                                if ui.checkbox(&mut muted, "mute").changed() {
+                                    settings.muted = muted;
+                                }
+                            });
+                            ui.end_row();
+                        });
+
+                        ui.horizontal(|ui| {
+                            if ui.button(RichText::new("Controls").size(30.0)).clicked() {
+                                *screen = MenuScreen::Controls;
+                            }
+                            if ui.button(RichText::new("Back").size(30.0)).clicked() {
+                                *screen = MenuScreen::Main;
+                            }
+                        });
+                    });
+                });
+        });
+}
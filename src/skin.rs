@@ -0,0 +1,189 @@
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::Path;
+use bevy::asset::LoadState;
+use bevy::prelude::*;
+use bevy::render::render_resource::FilterMode;
+use crate::field::{CellHandle, CellState, Field, Mark};
+
+/// Folder in `assets` that is searched for skins.
+#[cfg(not(target_arch = "wasm32"))]
+const SKIN_FOLDER: &str = "skins";
+/// The browser can't list folders, so the web build only knows the skins shipped with the game.
+#[cfg(target_arch = "wasm32")]
+const SKIN_FILES: [&str; 1] = ["skins/classic.png"];
+
+/// Tiles of a skin, in the order they appear in the sprite sheet. A skin is a single row of
+/// square tiles, so the tile size is the height of the image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tile {
+    Hidden,
+    Revealed,
+    Flag,
+    Question,
+    Mine,
+    Exploded,
+    WrongFlag,
+    Number(u8),
+}
+
+impl Tile {
+    pub const COUNT: usize = 15;
+
+    pub fn index(self) -> usize {
+        match self {
+            Tile::Hidden => 0,
+            Tile::Revealed => 1,
+            Tile::Flag => 2,
+            Tile::Question => 3,
+            Tile::Mine => 4,
+            Tile::Exploded => 5,
+            Tile::WrongFlag => 6,
+            Tile::Number(n) => 6 + n.clamp(1, 8) as usize,
+        }
+    }
+
+    pub fn for_cell(field: &mut Field, handle: CellHandle) -> Self {
+        let lost = field.detonated().is_some();
+        let is_mine = field[handle].is_mine;
+        match field[handle].get_state() {
+            CellState::Hidden => Tile::Hidden,
+            CellState::Marked(Mark::Mine) if lost && !is_mine => Tile::WrongFlag,
+            CellState::Marked(Mark::Mine) => Tile::Flag,
+            CellState::Marked(Mark::Question) => Tile::Question,
+            CellState::Revealed if is_mine => {
+                if field.detonated() == Some(handle) {
+                    Tile::Exploded
+                } else {
+                    Tile::Mine
+                }
+            }
+            CellState::Revealed => match field.get_adjacent_mines(handle) {
+                0 => Tile::Revealed,
+                n => Tile::Number(n as u8),
+            },
+        }
+    }
+}
+
+/// Sprite sheets found in `assets/skins` and the one in use. Without a selected skin the board is
+/// drawn with the flat colours of the current theme.
+pub struct Skins {
+    pub images: Vec<Handle<Image>>,
+    pub names: Vec<String>,
+    pub selected: Option<usize>,
+}
+
+impl Skins {
+    /// Loads every skin and selects the one called `selected` if it exists.
+    pub fn load(asset_server: &AssetServer, selected: Option<&str>) -> Self {
+        let images = Self::load_images(asset_server);
+        let names = images.iter()
+            .map(|handle| asset_server.get_handle_path(handle)
+                .and_then(|path| path.path().file_stem().map(|stem| stem.to_string_lossy().into_owned()))
+                .unwrap_or_else(|| "unnamed".to_owned()))
+            .collect::<Vec<_>>();
+        let selected = selected.and_then(|selected| names.iter().position(|name| name == selected));
+        Self {
+            images,
+            names,
+            selected,
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn load_images(asset_server: &AssetServer) -> Vec<Handle<Image>> {
+        match asset_server.load_folder(SKIN_FOLDER) {
+            Ok(handles) => handles.into_iter()
+                .filter(|handle| asset_server.get_handle_path(handle)
+                    .map_or(false, |path| Self::is_sprite_sheet(path.path())))
+                .map(|handle| handle.typed())
+                .collect(),
+            Err(e) => {
+                warn!("Could not load skins from {}: {:?}", SKIN_FOLDER, e);
+                Vec::new()
+            }
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn load_images(asset_server: &AssetServer) -> Vec<Handle<Image>> {
+        SKIN_FILES.iter().map(|path| asset_server.load(*path)).collect()
+    }
+
+    pub fn name(&self, selected: Option<usize>) -> &str {
+        selected.map_or("flat", |i| self.names[i].as_str())
+    }
+
+    /// Steps through the skins with the flat look as the entry before the first skin.
+    pub fn cycle(&mut self, delta: isize) {
+        let count = self.images.len() as isize + 1;
+        let current = self.selected.map_or(0, |i| i as isize + 1);
+        let next = (current + delta).rem_euclid(count);
+        self.selected = if next == 0 { None } else { Some(next as usize - 1) };
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn is_sprite_sheet(path: &Path) -> bool {
+        path.extension().map_or(false, |ext| ext == "png")
+    }
+}
+
+/// Atlas of the skin the board is currently drawn with, `None` for flat colours. Changes to this
+/// resource rebuild the cells of the board.
+#[derive(Debug, Default)]
+pub struct ActiveSkin(pub Option<Handle<TextureAtlas>>);
+
+/// Turns the selected skin into a texture atlas once its image has finished loading.
+pub(crate) fn prepare_skin(skins: Res<Skins>,
+                           asset_server: Res<AssetServer>,
+                           mut images: ResMut<Assets<Image>>,
+                           mut atlases: ResMut<Assets<TextureAtlas>>,
+                           mut active: ResMut<ActiveSkin>,
+                           mut pending: Local<bool>) {
+    if skins.is_changed() {
+        *pending = true;
+    }
+    if !*pending {
+        return;
+    }
+
+    let selected = match skins.selected {
+        Some(selected) => &skins.images[selected],
+        None => {
+            *pending = false;
+            fall_back_to_flat(&mut active);
+            return;
+        }
+    };
+    match asset_server.get_load_state(selected) {
+        LoadState::Loaded => {}
+        LoadState::Failed => {
+            warn!("Skin {} failed to load, using flat colours", skins.name(skins.selected));
+            *pending = false;
+            fall_back_to_flat(&mut active);
+            return;
+        }
+        _ => return,
+    }
+    if let Some(image) = images.get_mut(selected) {
+        *pending = false;
+        // keep pixel art crisp when the tiles are scaled up
+        image.sampler_descriptor.mag_filter = FilterMode::Nearest;
+        image.sampler_descriptor.min_filter = FilterMode::Nearest;
+        let tile = image.size().y;
+        if image.size().x < tile * Tile::COUNT as f32 {
+            warn!("Skin {} needs {} tiles of {}px in a row", skins.name(skins.selected), Tile::COUNT, tile);
+            fall_back_to_flat(&mut active);
+            return;
+        }
+        let atlas = TextureAtlas::from_grid(selected.clone(), Vec2::splat(tile), Tile::COUNT, 1);
+        active.0 = Some(atlases.add(atlas));
+    }
+}
+
+fn fall_back_to_flat(active: &mut ResMut<ActiveSkin>) {
+    // only touch the resource if needed, every change respawns the board
+    if active.0.is_some() {
+        active.0 = None;
+    }
+}
@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+use bevy::audio::{Audio, AudioSource};
+use bevy::prelude::*;
+use crate::field::RevealResult;
+use crate::settings::Settings;
+
+/// Sound played for something that happened on the board. Systems send these as events and
+/// `play_sounds` turns them into audio with the volume from the settings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SoundEffect {
+    Reveal,
+    FloodReveal,
+    Flag,
+    Chord,
+    Explosion,
+    Victory,
+}
+
+impl SoundEffect {
+    const ALL: [SoundEffect; 6] = [
+        SoundEffect::Reveal,
+        SoundEffect::FloodReveal,
+        SoundEffect::Flag,
+        SoundEffect::Chord,
+        SoundEffect::Explosion,
+        SoundEffect::Victory,
+    ];
+
+    fn path(self) -> &'static str {
+        match self {
+            SoundEffect::Reveal => "sounds/reveal.wav",
+            SoundEffect::FloodReveal => "sounds/flood_reveal.wav",
+            SoundEffect::Flag => "sounds/flag.wav",
+            SoundEffect::Chord => "sounds/chord.wav",
+            SoundEffect::Explosion => "sounds/explosion.wav",
+            SoundEffect::Victory => "sounds/victory.wav",
+        }
+    }
+
+    /// Sound for revealing a single cell. Mines stay silent here, the explosion is played once the
+    /// game is lost.
+    pub fn for_reveal(result: &RevealResult) -> Option<Self> {
+        match result {
+            RevealResult::Empty(0) => Some(SoundEffect::FloodReveal),
+            RevealResult::Empty(_) => Some(SoundEffect::Reveal),
+            RevealResult::Mine | RevealResult::AlreadyRevealed | RevealResult::GameEnded => None,
+        }
+    }
+}
+
+pub struct Sounds {
+    sources: HashMap<SoundEffect, Handle<AudioSource>>,
+    /// Copies of the sources at `volume`. Bevy 0.7 always plays at full volume, so the samples
+    /// themselves are scaled.
+    scaled: HashMap<SoundEffect, Handle<AudioSource>>,
+    volume: f32,
+}
+
+impl Sounds {
+    pub fn load(asset_server: &AssetServer) -> Self {
+        Self {
+            sources: SoundEffect::ALL.iter().map(|&effect| (effect, asset_server.load(effect.path()))).collect(),
+            scaled: HashMap::new(),
+            volume: 1.0,
+        }
+    }
+
+    /// Sound of `effect` at `volume`, `None` while the file is still loading.
+    fn handle(&mut self, effect: SoundEffect, volume: f32, assets: &mut Assets<AudioSource>) -> Option<Handle<AudioSource>> {
+        let source = self.sources.get(&effect)?;
+        if volume >= 1.0 {
+            return Some(source.clone());
+        }
+        if self.volume != volume {
+            self.scaled.clear();
+            self.volume = volume;
+        }
+        if let Some(handle) = self.scaled.get(&effect) {
+            return Some(handle.clone());
+        }
+        let handle = match scale_wav(&assets.get(source)?.bytes, volume) {
+            Some(bytes) => assets.add(AudioSource { bytes: bytes.into() }),
+            None => {
+                warn!("{} is not a 16 bit PCM wav file, it is played at full volume", effect.path());
+                source.clone()
+            }
+        };
+        self.scaled.insert(effect, handle.clone());
+        Some(handle)
+    }
+}
+
+/// Copy of a 16 bit PCM wav file with every sample multiplied by `volume`.
+fn scale_wav(bytes: &[u8], volume: f32) -> Option<Vec<u8>> {
+    if bytes.get(0..4)? != b"RIFF" || bytes.get(8..12)? != b"WAVE" {
+        return None;
+    }
+    let mut pcm16 = false;
+    let mut pos = 12;
+    while let Some(header) = bytes.get(pos..pos + 8) {
+        let len = u32::from_le_bytes([header[4], header[5], header[6], header[7]]) as usize;
+        let body = pos + 8;
+        match &header[0..4] {
+            b"fmt " => {
+                let fmt = bytes.get(body..body + 16)?;
+                // format 1 is uncompressed PCM
+                pcm16 = u16::from_le_bytes([fmt[0], fmt[1]]) == 1 && u16::from_le_bytes([fmt[14], fmt[15]]) == 16;
+            }
+            b"data" if pcm16 => {
+                let mut scaled = bytes.to_vec();
+                let end = (body + len).min(bytes.len());
+                for sample in scaled[body..end].chunks_exact_mut(2) {
+                    let value = i16::from_le_bytes([sample[0], sample[1]]) as f32 * volume;
+                    sample.copy_from_slice(&(value as i16).to_le_bytes());
+                }
+                return Some(scaled);
+            }
+            b"data" => return None,
+            _ => {}
+        }
+        // chunks are padded to an even length
+        pos = body + len + (len & 1);
+    }
+    None
+}
+
+pub(crate) fn play_sounds(mut events: EventReader<SoundEffect>,
+                          mut sounds: ResMut<Sounds>,
+                          mut assets: ResMut<Assets<AudioSource>>,
+                          audio: Res<Audio>,
+                          settings: Res<Settings>) {
+    for &effect in events.iter() {
+        if settings.muted || settings.volume <= 0.0 {
+            continue;
+        }
+        if let Some(handle) = sounds.handle(effect, settings.volume, &mut assets) {
+            audio.play(handle);
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+use bevy::asset::{AssetLoader, BoxedFuture, LoadContext, LoadedAsset};
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use serde::{Deserialize, Serialize};
+use crate::{ExplodedColor, Field, HiddenColor, HoverColor, MarkedMineColor, MineColor, QuestionColor, RevealedColor, StateMaterials};
+
+/// Themes shipped in `assets/themes`, in the order they are listed in the menu.
+pub(crate) const THEME_FILES: [&str; 3] = [
+    "themes/classic.theme.ron",
+    "themes/dark.theme.ron",
+    "themes/high_contrast.theme.ron",
+];
+
+/// Colour palette of the board, loaded from `*.theme.ron` files.
+#[derive(Debug, Clone, Deserialize, Serialize, TypeUuid)]
+#[uuid = "ee5676d1-82cf-450b-a866-da53d4e48a30"]
+pub struct Theme {
+    pub name: String,
+    pub background: Color,
+    pub hidden: Color,
+    /// Hidden cell below the mouse, also used to tint skins.
+    pub hover: Color,
+    pub revealed: Color,
+    pub mine: Color,
+    pub exploded: Color,
+    pub marked_mine: Color,
+    /// Colour of the question mark drawn on top of a hidden cell.
+    pub question: Color,
+    pub numbers: [Color; 8],
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            name: "Default".to_owned(),
+            background: Color::rgb(0.9, 0.9, 0.9),
+            hidden: Color::rgb(0.4, 0.4, 0.4),
+            hover: Color::rgb(0.55, 0.55, 0.55),
+            revealed: Color::rgb(0.8, 0.8, 0.8),
+            mine: Color::rgb(0.8, 0.0, 0.0),
+            exploded: Color::rgb(1.0, 0.5, 0.0),
+            marked_mine: Color::rgb(0.6, 0.1, 0.1),
+            question: Color::rgb(0.1, 0.1, 0.1),
+            numbers: [
+                Color::rgb(0.0, 0.0, 1.0),
+                Color::rgb(0.0, 1.0, 0.0),
+                Color::rgb(0.0, 1.0, 1.0),
+                Color::rgb(1.0, 0.0, 0.0),
+                Color::rgb(1.0, 0.0, 1.0),
+                Color::rgb(1.0, 1.0, 0.0),
+                Color::rgb(1.0, 1.0, 1.0),
+                Color::rgb(0.5, 0.5, 0.5),
+            ],
+        }
+    }
+}
+
+impl From<&Theme> for StateMaterials {
+    fn from(theme: &Theme) -> Self {
+        StateMaterials {
+            revealed: RevealedColor(theme.revealed),
+            hidden: HiddenColor(theme.hidden),
+            hover: HoverColor(theme.hover),
+            mine: MineColor(theme.mine),
+            exploded: ExplodedColor(theme.exploded),
+            marked_mine: MarkedMineColor(theme.marked_mine),
+            question: QuestionColor(theme.question),
+            colors_numbers: theme.numbers.iter().enumerate()
+                .map(|(i, color)| (i as u8 + 1, *color))
+                .collect::<HashMap<_, _>>(),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct ThemeLoader;
+
+impl AssetLoader for ThemeLoader {
+    fn load<'a>(&'a self, bytes: &'a [u8], load_context: &'a mut LoadContext) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let theme: Theme = ron::de::from_bytes(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(theme));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["theme.ron"]
+    }
+}
+
+/// All known themes and the one in use. Themes are applied as soon as they finish loading and
+/// again whenever the asset server reports a change to the file.
+pub struct Themes {
+    pub handles: Vec<Handle<Theme>>,
+    pub selected: usize,
+}
+
+impl Themes {
+    /// Loads every theme and selects the one at `selected`, the first if it is unknown.
+    pub fn load(asset_server: &AssetServer, selected: &str) -> Self {
+        Self {
+            handles: THEME_FILES.iter().map(|path| asset_server.load(*path)).collect(),
+            selected: THEME_FILES.iter().position(|path| *path == selected).unwrap_or(0),
+        }
+    }
+
+    pub fn name(&self, index: usize, themes: &Assets<Theme>) -> String {
+        themes.get(&self.handles[index])
+            .map(|theme| theme.name.clone())
+            .unwrap_or_else(|| THEME_FILES[index].trim_start_matches("themes/").trim_end_matches(".theme.ron").to_owned())
+    }
+}
+
+pub(crate) fn apply_theme(themes: Res<Themes>,
+                          theme_assets: Res<Assets<Theme>>,
+                          mut events: EventReader<AssetEvent<Theme>>,
+                          mut state_materials: ResMut<StateMaterials>,
+                          mut clear_color: ResMut<ClearColor>,
+                          field: Option<ResMut<Field>>) {
+    let selected = &themes.handles[themes.selected];
+    let reloaded = events.iter().any(|event| match event {
+        AssetEvent::Created { handle } | AssetEvent::Modified { handle } => handle == selected,
+        AssetEvent::Removed { .. } => false,
+    });
+    if !reloaded && !themes.is_changed() {
+        return;
+    }
+
+    if let Some(theme) = theme_assets.get(selected) {
+        info!("Applying theme {}", theme.name);
+        *state_materials = StateMaterials::from(theme);
+        clear_color.0 = theme.background;
+        if let Some(mut field) = field {
+            field.mark_all_changed();
+        }
+    }
+}
@@ -0,0 +1,179 @@
+use std::collections::HashMap;
+use bevy::prelude::*;
+use bevy_egui::{egui, EguiContext};
+use bevy_egui::egui::{Align2, RichText};
+use crate::{CameraOptions, Field, MainCam};
+use crate::animation::RippleOrigin;
+use crate::camera::CameraView;
+use crate::interactions::{MousePositionToCellConverter, reveal_cell, toggle_mark};
+use crate::settings::Settings;
+use crate::sound::SoundEffect;
+
+const LONG_PRESS: f64 = 0.5;
+const DRAG_THRESHOLD: f32 = 12.0;
+const MOUSE_SUPPRESSION: f64 = 1.0;
+
+/// What a tap on a hidden cell does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TouchMode {
+    Reveal,
+    Flag,
+}
+
+impl Default for TouchMode {
+    fn default() -> Self {
+        TouchMode::Reveal
+    }
+}
+
+/// State of the touches currently on the screen. A touch only counts as a tap if it was released
+/// without moving, without a second finger joining and before it turned into a long press.
+#[derive(Debug, Default)]
+pub struct TouchTracker {
+    pub mode: TouchMode,
+    started: HashMap<u64, f64>,
+    consumed: Vec<u64>,
+    pinch_distance: Option<f32>,
+    last_touch: Option<f64>,
+    toggle_rect: Option<egui::Rect>,
+}
+
+impl TouchTracker {
+    pub fn recently_touched(&self, now: f64) -> bool {
+        self.last_touch.map_or(false, |t| now - t < MOUSE_SUPPRESSION)
+    }
+
+    fn consume(&mut self, id: u64) {
+        if !self.consumed.contains(&id) {
+            self.consumed.push(id);
+        }
+    }
+
+    fn toggle_mode(&mut self) {
+        self.mode = match self.mode {
+            TouchMode::Reveal => TouchMode::Flag,
+            TouchMode::Flag => TouchMode::Reveal,
+        };
+    }
+
+    fn is_on_toggle(&self, position: Vec2) -> bool {
+        self.toggle_rect.map_or(false, |r| r.contains(egui::pos2(position.x, position.y)))
+    }
+}
+
+pub(crate) fn update_touch(touches: Res<Touches>,
+                           time: Res<Time>,
+                           mut tracker: ResMut<TouchTracker>,
+                           settings: Res<Settings>,
+                           mut origin: ResMut<RippleOrigin>,
+                           mut sounds: EventWriter<SoundEffect>,
+                           mut field: ResMut<Field>,
+                           mut view: ResMut<CameraView>,
+                           camera_options: Res<CameraOptions>,
+                           converter: Res<MousePositionToCellConverter>,
+                           cam_query: Query<(&Camera, &GlobalTransform), With<MainCam>>,
+                           windows: Res<Windows>) {
+    let now = time.seconds_since_startup();
+    let (cam, trans) = cam_query.single();
+    let wnd = converter.get_window(cam, &windows);
+    // touch positions start at the top left, the camera math expects the bottom left
+    let to_window = |position: Vec2| Vec2::new(position.x, wnd.height() - position.y);
+
+    for touch in touches.iter_just_pressed() {
+        tracker.started.insert(touch.id(), now);
+        tracker.last_touch = Some(now);
+        if tracker.is_on_toggle(touch.position()) {
+            tracker.consume(touch.id());
+        }
+    }
+
+    let active = touches.iter().collect::<Vec<_>>();
+    if active.len() >= 2 {
+        // pinch to zoom and drag with two fingers to pan
+        active.iter().for_each(|t| tracker.consume(t.id()));
+        let distance = active[0].position().distance(active[1].position());
+        if let Some(previous) = tracker.pinch_distance {
+            if previous > 0.0 {
+                view.zoom_by(distance / previous);
+            }
+        }
+        tracker.pinch_distance = Some(distance);
+        let delta = (active[0].delta() + active[1].delta()) / 2.0;
+        pan(&mut view, delta, wnd, &camera_options);
+    } else {
+        tracker.pinch_distance = None;
+        if let Some(touch) = active.first() {
+            tracker.last_touch = Some(now);
+            let moved = touch.distance().length() > DRAG_THRESHOLD;
+            if moved {
+                tracker.consume(touch.id());
+                pan(&mut view, touch.delta(), wnd, &camera_options);
+            } else if !tracker.consumed.contains(&touch.id())
+                && tracker.started.get(&touch.id()).map_or(false, |&start| now - start > LONG_PRESS) {
+                tracker.consume(touch.id());
+                if let Some(cell) = converter.get_cell_at(cam, trans, wnd, to_window(touch.start_position()), &camera_options, &field) {
+                    toggle_mark(&mut field, cell, &settings, &mut sounds);
+                }
+            }
+        }
+    }
+
+    for touch in touches.iter_just_released() {
+        tracker.started.remove(&touch.id());
+        if tracker.is_on_toggle(touch.start_position()) {
+            tracker.toggle_mode();
+            continue;
+        }
+        if tracker.consumed.contains(&touch.id()) {
+            continue;
+        }
+        if let Some(cell) = converter.get_cell_at(cam, trans, wnd, to_window(touch.start_position()), &camera_options, &field) {
+            origin.0 = Some(cell);
+            match tracker.mode {
+                TouchMode::Reveal => {
+                    if let Some(sound) = SoundEffect::for_reveal(&reveal_cell(&mut field, cell)) {
+                        sounds.send(sound);
+                    }
+                }
+                TouchMode::Flag => toggle_mark(&mut field, cell, &settings, &mut sounds),
+            }
+        }
+    }
+    for touch in touches.iter_just_cancelled() {
+        tracker.started.remove(&touch.id());
+    }
+    if active.is_empty() {
+        tracker.consumed.clear();
+    }
+}
+
+fn pan(view: &mut CameraView, delta: Vec2, wnd: &Window, camera_options: &CameraOptions) {
+    if delta == Vec2::ZERO {
+        return;
+    }
+    let world_per_pixel = camera_options.scale / view.zoom / wnd.height();
+    // the content follows the finger, touch y points down
+    view.focus.x -= delta.x * world_per_pixel;
+    view.focus.y += delta.y * world_per_pixel;
+    view.focus = view.focus.clamp(Vec2::ZERO, Vec2::new(camera_options.width, camera_options.height));
+}
+
+pub(crate) fn show_touch_controls(mut egui_ctx: ResMut<EguiContext>, mut tracker: ResMut<TouchTracker>, time: Res<Time>) {
+    // the browser build always offers the toggle, mobile browsers may only send emulated clicks
+    if tracker.last_touch.is_none() && !cfg!(target_arch = "wasm32") {
+        return;
+    }
+    let text = match tracker.mode {
+        TouchMode::Reveal => "Mode: reveal",
+        TouchMode::Flag => "Mode: flag",
+    };
+    let response = egui::Area::new("TouchControls")
+        .anchor(Align2::RIGHT_BOTTOM, egui::vec2(-10.0, -10.0))
+        .show(egui_ctx.ctx_mut(), |ui| {
+            // egui doesn't see touches, so only emulated mouse clicks end up here
+            if ui.button(RichText::new(text).size(40.0)).clicked() && !tracker.recently_touched(time.seconds_since_startup()) {
+                tracker.toggle_mode();
+            }
+        }).response;
+    tracker.toggle_rect = Some(response.rect);
+}
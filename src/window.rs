@@ -0,0 +1,209 @@
+use std::io;
+use bevy::prelude::*;
+use bevy::window::{PresentMode, WindowMode};
+#[cfg(not(target_arch = "wasm32"))]
+use bevy::window::WindowResized;
+use serde::{Deserialize, Serialize};
+use crate::config::load_or_default;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::config::save_config;
+
+const WINDOW_CONFIG: &str = "window";
+/// Time without further changes before the window config is written, so dragging the window
+/// border doesn't write the file every frame.
+#[cfg(not(target_arch = "wasm32"))]
+const SAVE_DELAY: f64 = 0.5;
+
+/// Layout of `config/window.ron`, the window settings are nested under `window`.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+struct WindowConfigFile {
+    window: WindowConfig,
+}
+
+/// Window settings as stored in `config/window.ron`. Kept as a resource so changes made in the
+/// game can be written back.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct WindowConfig {
+    pub width: f32,
+    pub height: f32,
+    pub title: String,
+    pub mode: WindowModeConfig,
+    pub transparent: bool,
+    pub resizable: bool,
+    pub decorations: bool,
+    #[serde(default = "default_vsync")]
+    pub vsync: bool,
+}
+
+fn default_vsync() -> bool {
+    true
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        WindowDescriptor {
+            height: 800.0,
+            width: 800.0,
+            title: "Minesweeper".to_owned(),
+            ..default()
+        }.into()
+    }
+}
+
+impl WindowConfig {
+    /// Loads the window config, falling back to the defaults if the file is missing or broken.
+    /// The error is handed back as well because logging isn't set up yet when the window is
+    /// configured.
+    pub fn load_or_default() -> (Self, Option<io::Error>) {
+        let (file, error) = load_or_default::<WindowConfigFile>(WINDOW_CONFIG);
+        // fail early on values the descriptor can't take
+        match WindowMode::try_from(file.window.mode.clone()) {
+            Ok(_) => (file.window, error),
+            Err(e) => (Self::default(), Some(e)),
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save(&self) -> io::Result<()> {
+        save_config(WINDOW_CONFIG, &WindowConfigFile { window: self.clone() })
+    }
+}
+
+impl From<WindowDescriptor> for WindowConfig {
+    fn from(descriptor: WindowDescriptor) -> Self {
+        Self {
+            width: descriptor.width,
+            height: descriptor.height,
+            title: descriptor.title,
+            mode: descriptor.mode.into(),
+            transparent: descriptor.transparent,
+            resizable: descriptor.resizable,
+            decorations: descriptor.decorations,
+            vsync: descriptor.present_mode == PresentMode::Fifo,
+        }
+    }
+}
+
+impl TryFrom<WindowConfig> for WindowDescriptor {
+    type Error = io::Error;
+
+    fn try_from(value: WindowConfig) -> Result<Self, Self::Error> {
+        Ok(Self {
+            width: value.width,
+            height: value.height,
+            title: value.title,
+            mode: value.mode.try_into()?,
+            transparent: value.transparent,
+            resizable: value.resizable,
+            decorations: value.decorations,
+            present_mode: present_mode(value.vsync),
+            ..default()
+        })
+    }
+}
+
+fn present_mode(vsync: bool) -> PresentMode {
+    if vsync {
+        PresentMode::Fifo
+    } else {
+        PresentMode::Immediate
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct WindowModeConfig(String);
+
+impl WindowModeConfig {
+    pub const ALL: [WindowMode; 4] = [
+        WindowMode::Windowed,
+        WindowMode::BorderlessFullscreen,
+        WindowMode::SizedFullscreen,
+        WindowMode::Fullscreen,
+    ];
+
+    pub fn name(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<WindowMode> for WindowModeConfig {
+    fn from(mode: WindowMode) -> Self {
+        match mode {
+            WindowMode::Windowed => WindowModeConfig("Windowed".to_owned()),
+            WindowMode::BorderlessFullscreen => WindowModeConfig("BorderlessFullscreen".to_owned()),
+            WindowMode::SizedFullscreen => WindowModeConfig("SizedFullscreen".to_owned()),
+            WindowMode::Fullscreen => WindowModeConfig("Fullscreen".to_owned()),
+        }
+    }
+}
+
+impl TryFrom<WindowModeConfig> for WindowMode {
+    type Error = io::Error;
+
+    fn try_from(value: WindowModeConfig) -> Result<Self, Self::Error> {
+        match value.0.as_str() {
+            "Windowed" => Ok(WindowMode::Windowed),
+            "BorderlessFullscreen" => Ok(WindowMode::BorderlessFullscreen),
+            "SizedFullscreen" => Ok(WindowMode::SizedFullscreen),
+            "Fullscreen" => Ok(WindowMode::Fullscreen),
+            other => Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                        format!("Invalid window mode {:?}, expected Windowed, BorderlessFullscreen, SizedFullscreen or Fullscreen", other))),
+        }
+    }
+}
+
+/// Keeps the window config in line with the actual window and writes it once things settle down.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn persist_window_config(mut config: ResMut<WindowConfig>,
+                                    mut resized: EventReader<WindowResized>,
+                                    windows: Res<Windows>,
+                                    time: Res<Time>,
+                                    mut pending: Local<Option<f64>>) {
+    let now = time.seconds_since_startup();
+    let primary = windows.get_primary().map(|w| w.id());
+    for event in resized.iter().filter(|e| Some(e.id) == primary) {
+        // the size of a fullscreen window says nothing about the windowed size
+        if config.mode == WindowMode::Windowed.into() && (config.width != event.width || config.height != event.height) {
+            config.width = event.width;
+            config.height = event.height;
+        }
+    }
+    if config.is_changed() && !config.is_added() {
+        *pending = Some(now);
+    }
+
+    if let Some(changed) = *pending {
+        if now - changed > SAVE_DELAY {
+            *pending = None;
+            if let Err(e) = config.save() {
+                warn!("Could not save window config: {}", e);
+            }
+        }
+    }
+}
+
+/// Applies changes made to the window config in game to the primary window.
+pub(crate) fn apply_window_config(config: Res<WindowConfig>, mut windows: ResMut<Windows>) {
+    if !config.is_changed() || config.is_added() {
+        return;
+    }
+    let window = match windows.get_primary_mut() {
+        Some(window) => window,
+        None => return,
+    };
+    match WindowMode::try_from(config.mode.clone()) {
+        Ok(mode) => {
+            if window.mode() != mode {
+                window.set_mode(mode);
+            }
+            if mode == WindowMode::Windowed {
+                window.set_resolution(config.width, config.height);
+            }
+        }
+        Err(e) => warn!("{}", e),
+    }
+    let present_mode = present_mode(config.vsync);
+    if window.present_mode() != present_mode {
+        window.set_present_mode(present_mode);
+    }
+}